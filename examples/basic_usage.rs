@@ -1,4 +1,7 @@
-use template_rust::{database::TodoDatabase, models::Todo};
+use template_rust::{
+    database::{Sort, TodoDatabase},
+    models::{Status, Todo},
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -25,8 +28,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("All todos:");
     let todos = db.get_all_todos().await?;
     for todo in &todos {
-        let status = if todo.completed { "✓" } else { "○" };
-        println!("  {} {}", status, todo.title);
+        println!("  {} {}", todo.status.symbol(), todo.title);
         if let Some(description) = &todo.description {
             println!("    {}", description);
         }
@@ -40,13 +42,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\nAfter completing first todo:");
     let updated_todos = db.get_all_todos().await?;
     for todo in &updated_todos {
-        let status = if todo.completed { "✓" } else { "○" };
-        println!("  {} {}", status, todo.title);
+        println!("  {} {}", todo.status.symbol(), todo.title);
     }
 
     // Get only pending todos
     println!("\nPending todos:");
-    let pending_todos = db.get_todos_by_status(false).await?;
+    let pending_todos = db.list_todos(&Status::OPEN, &Sort::default()).await?;
     for todo in &pending_todos {
         println!("  ○ {}", todo.title);
     }
@@ -0,0 +1,730 @@
+//! Layered configuration
+//!
+//! Settings are merged from these sources, later ones taking precedence:
+//!
+//! 1. built-in defaults; the database lives in `$XDG_DATA_HOME/todo/todo.db`
+//!    (`~/.local/share/todo/todo.db` when `XDG_DATA_HOME` is unset)
+//! 2. the user config file, `$XDG_CONFIG_HOME/todo/config.toml`
+//!    (`~/.config/todo/config.toml` when `XDG_CONFIG_HOME` is unset)
+//! 3. a `.todo` marker in the current directory or one of its parents, which
+//!    opts that directory into a local database (see [`Config::marker_database`])
+//! 4. the project config file, the nearest `.todo.toml` in the current
+//!    directory or one of its parents
+//! 5. `TODO_*` environment variables, e.g. `TODO_DATABASE` or `TODO_SORT`
+//! 6. command line flags, applied by the binary
+//!
+//! The passphrase of an encrypted database is only read from `TODO_PASSPHRASE`,
+//! so it never ends up in a config file; it takes precedence over `key_file`.
+
+mod ui;
+
+pub use ui::{Filter, KeyName, Keymap, Theme};
+
+use crate::database::{DatabaseOptions, Key, Sort};
+use crate::models::{parse_age, Status, TransitionRules};
+use crate::Result;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Name of the project config file looked up from the current directory upwards
+pub const PROJECT_FILE: &str = ".todo.toml";
+
+/// Name of the marker that opts a directory tree into a local database
+pub const MARKER: &str = ".todo";
+
+/// Keys that can be read with `config get` and written with `config set`.
+/// Key bindings are set as `keymap.<key>` and WIP limits as `wip_limits.<status>`.
+pub const KEYS: [&str; 14] = [
+    "database",
+    "filter",
+    "sort",
+    "theme",
+    "keymap",
+    "output",
+    "identity",
+    "auto_archive",
+    "wal",
+    "busy_timeout",
+    "busy_retries",
+    "key_file",
+    "transitions",
+    "wip_limits",
+];
+
+/// How CLI commands print todos and log entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+        })
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => anyhow::bail!("unknown output format: {} (expected text or json)", other),
+        }
+    }
+}
+
+/// Raw settings from one source, as written in a config file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub database: Option<String>,
+    pub filter: Option<String>,
+    pub sort: Option<String>,
+    pub theme: Option<String>,
+    /// Extra TUI key bindings, key to built-in key, e.g. `j = "down"`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keymap: BTreeMap<String, String>,
+    pub output: Option<String>,
+    pub identity: Option<String>,
+    pub auto_archive: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub wal: Option<String>,
+    /// Milliseconds
+    #[serde(deserialize_with = "scalar")]
+    pub busy_timeout: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub busy_retries: Option<String>,
+    /// File holding the key of an encrypted database
+    pub key_file: Option<String>,
+    /// Allowed status transitions, e.g. `"todo:in_progress;in_progress:done"`
+    pub transitions: Option<String>,
+    /// Most todos allowed in a TUI board column, status to limit, e.g. `in_progress = 3`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub wip_limits: BTreeMap<String, usize>,
+}
+
+/// Read a setting written as a string, number or boolean, e.g. `wal = false`
+fn scalar<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scalar {
+        Text(String),
+        Integer(i64),
+        Boolean(bool),
+    }
+    Ok(
+        Option::<Scalar>::deserialize(deserializer)?.map(|value| match value {
+            Scalar::Text(text) => text,
+            Scalar::Integer(number) => number.to_string(),
+            Scalar::Boolean(flag) => flag.to_string(),
+        }),
+    )
+}
+
+impl ConfigFile {
+    /// The built-in defaults, written out as settings
+    pub fn defaults() -> Self {
+        let config = Config::default();
+        Self {
+            database: Some(config.database_url),
+            filter: Some("all".to_string()),
+            sort: Some(config.sort.to_string()),
+            theme: Some(config.theme.to_string()),
+            output: Some(config.output.to_string()),
+            wal: Some(config.database_options.wal.to_string()),
+            busy_timeout: Some(config.database_options.busy_timeout.as_millis().to_string()),
+            busy_retries: Some(config.database_options.busy_retries.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Read a config file, returning `None` if it does not exist
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(path)?;
+        let file = toml::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("invalid config file {}: {}", path.display(), e))?;
+        Ok(Some(file))
+    }
+
+    /// These settings with relative paths taken relative to `dir`, the
+    /// directory of the file they were read from, rather than to wherever the
+    /// command happens to run
+    pub fn relative_to(mut self, dir: &Path) -> Self {
+        for path in [&mut self.database, &mut self.key_file]
+            .into_iter()
+            .flatten()
+        {
+            let is_url = path == ":memory:" || path.starts_with("sqlite:");
+            if !is_url && Path::new(path.as_str()).is_relative() {
+                *path = dir.join(path.as_str()).display().to_string();
+            }
+        }
+        self
+    }
+
+    /// Settings from `TODO_*` variables looked up with `var`; `TODO_KEYMAP`
+    /// holds comma separated bindings such as `j=down,k=up`, and
+    /// `TODO_WIP_LIMITS` limits such as `in_progress=3,blocked=2`
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let pairs = |name: &str| -> Result<Vec<(String, String)>> {
+            let Some(value) = var(name) else {
+                return Ok(Vec::new());
+            };
+            value
+                .split(',')
+                .filter(|pair| !pair.trim().is_empty())
+                .map(|pair| {
+                    let (key, value) = pair
+                        .split_once('=')
+                        .ok_or_else(|| anyhow::anyhow!("invalid {} entry: {}", name, pair))?;
+                    Ok((key.trim().to_string(), value.trim().to_string()))
+                })
+                .collect()
+        };
+        let keymap = pairs("TODO_KEYMAP")?.into_iter().collect();
+        let wip_limits = pairs("TODO_WIP_LIMITS")?
+            .into_iter()
+            .map(|(status, limit)| {
+                let limit = limit.parse().map_err(|e| {
+                    anyhow::anyhow!("invalid TODO_WIP_LIMITS limit for {}: {}", status, e)
+                })?;
+                Ok((status, limit))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            database: var("TODO_DATABASE"),
+            filter: var("TODO_FILTER"),
+            sort: var("TODO_SORT"),
+            theme: var("TODO_THEME"),
+            keymap,
+            output: var("TODO_OUTPUT"),
+            identity: var("TODO_IDENTITY"),
+            auto_archive: var("TODO_AUTO_ARCHIVE"),
+            wal: var("TODO_WAL"),
+            busy_timeout: var("TODO_BUSY_TIMEOUT"),
+            busy_retries: var("TODO_BUSY_RETRIES"),
+            key_file: var("TODO_KEY_FILE"),
+            transitions: var("TODO_TRANSITIONS"),
+            wip_limits,
+        })
+    }
+
+    /// Overlay `other` on top of these settings
+    pub fn merge(&mut self, other: ConfigFile) {
+        fn overlay(value: &mut Option<String>, other: Option<String>) {
+            if other.is_some() {
+                *value = other;
+            }
+        }
+        overlay(&mut self.database, other.database);
+        overlay(&mut self.filter, other.filter);
+        overlay(&mut self.sort, other.sort);
+        overlay(&mut self.theme, other.theme);
+        overlay(&mut self.output, other.output);
+        overlay(&mut self.identity, other.identity);
+        overlay(&mut self.auto_archive, other.auto_archive);
+        overlay(&mut self.wal, other.wal);
+        overlay(&mut self.busy_timeout, other.busy_timeout);
+        overlay(&mut self.busy_retries, other.busy_retries);
+        overlay(&mut self.key_file, other.key_file);
+        overlay(&mut self.transitions, other.transitions);
+        self.keymap.extend(other.keymap);
+        self.wip_limits.extend(other.wip_limits);
+    }
+
+    /// Value of a setting as written, `keymap.<key>` for a single binding and
+    /// `wip_limits.<status>` for a single limit
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        if let Some(binding) = key.strip_prefix("keymap.") {
+            return Ok(self.keymap.get(binding).cloned());
+        }
+        if let Some(status) = key.strip_prefix("wip_limits.") {
+            return Ok(self.wip_limits.get(status).map(ToString::to_string));
+        }
+        Ok(match key {
+            "database" => self.database.clone(),
+            "filter" => self.filter.clone(),
+            "sort" => self.sort.clone(),
+            "theme" => self.theme.clone(),
+            "keymap" => Some(
+                self.keymap
+                    .iter()
+                    .map(|(key, target)| format!("{}={}", key, target))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            )
+            .filter(|bindings| !bindings.is_empty()),
+            "output" => self.output.clone(),
+            "identity" => self.identity.clone(),
+            "auto_archive" => self.auto_archive.clone(),
+            "wal" => self.wal.clone(),
+            "busy_timeout" => self.busy_timeout.clone(),
+            "busy_retries" => self.busy_retries.clone(),
+            "key_file" => self.key_file.clone(),
+            "transitions" => self.transitions.clone(),
+            "wip_limits" => Some(
+                self.wip_limits
+                    .iter()
+                    .map(|(status, limit)| format!("{}={}", status, limit))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            )
+            .filter(|limits| !limits.is_empty()),
+            other => anyhow::bail!(
+                "unknown config key: {} (expected one of {})",
+                other,
+                KEYS.join(", ")
+            ),
+        })
+    }
+
+    /// Change a setting, validating the value first
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut updated = self.clone();
+        let value = value.to_string();
+        if let Some(binding) = key.strip_prefix("keymap.") {
+            updated.keymap.insert(binding.to_string(), value);
+        } else if let Some(status) = key.strip_prefix("wip_limits.") {
+            let limit = value
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid config value for {}: {}", key, e))?;
+            updated.wip_limits.insert(status.to_string(), limit);
+        } else {
+            let slot = match key {
+                "database" => &mut updated.database,
+                "filter" => &mut updated.filter,
+                "sort" => &mut updated.sort,
+                "theme" => &mut updated.theme,
+                "output" => &mut updated.output,
+                "identity" => &mut updated.identity,
+                "auto_archive" => &mut updated.auto_archive,
+                "wal" => &mut updated.wal,
+                "busy_timeout" => &mut updated.busy_timeout,
+                "busy_retries" => &mut updated.busy_retries,
+                "key_file" => &mut updated.key_file,
+                "transitions" => &mut updated.transitions,
+                "keymap" => anyhow::bail!("set key bindings one at a time as keymap.<key>"),
+                "wip_limits" => {
+                    anyhow::bail!("set WIP limits one at a time as wip_limits.<status>")
+                }
+                other => anyhow::bail!(
+                    "unknown config key: {} (expected one of {})",
+                    other,
+                    KEYS.join(", ")
+                ),
+            };
+            *slot = Some(value);
+        }
+        Config::resolve(&updated)?;
+        *self = updated;
+        Ok(())
+    }
+
+    /// Write the settings to `path`, creating its directory if needed
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Resolved application configuration
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Database file path
+    pub database_url: String,
+    /// Filter the TUI starts with
+    pub filter: Filter,
+    pub sort: Sort,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    pub output: OutputFormat,
+    /// Name recorded in the audit log; the OS user when unset
+    pub identity: Option<String>,
+    /// Archive finished todos older than this on every run
+    pub auto_archive: Option<chrono::Duration>,
+    /// Journaling and lock handling, for sharing the database between processes
+    pub database_options: DatabaseOptions,
+    pub transitions: TransitionRules,
+    /// Most todos allowed in each TUI board column
+    pub wip_limits: Vec<(Status, usize)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database_url: default_database_path()
+                .map_or_else(|| "todo.db".to_string(), |path| path.display().to_string()),
+            filter: Filter::All,
+            sort: Sort::default(),
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            output: OutputFormat::default(),
+            identity: None,
+            auto_archive: None,
+            database_options: DatabaseOptions::default(),
+            transitions: TransitionRules::permissive(),
+            wip_limits: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration from the user file, the project file found from
+    /// the current directory, and the environment
+    pub fn load() -> Result<Self> {
+        let mut config = Self::resolve(&Self::sources()?)?;
+        if let Some(passphrase) = std::env::var("TODO_PASSPHRASE")
+            .ok()
+            .filter(|passphrase| !passphrase.is_empty())
+        {
+            config.database_options.key = Some(Key::Passphrase(passphrase));
+        }
+        Ok(config)
+    }
+
+    /// The merged raw settings of the defaults and all config sources
+    pub fn sources() -> Result<ConfigFile> {
+        let mut merged = ConfigFile::default();
+        for (_, layer) in Self::layers()? {
+            merged.merge(layer);
+        }
+        Ok(merged)
+    }
+
+    /// Every config source that applies in the current directory, named and in
+    /// increasing order of precedence
+    pub fn layers() -> Result<Vec<(String, ConfigFile)>> {
+        Self::layers_in(&std::env::current_dir()?)
+    }
+
+    /// Every config source that applies in `cwd`, see [`Config::layers`]
+    ///
+    /// Relative paths in config files are relative to the file's directory,
+    /// so a project's settings find the same database from any subdirectory.
+    fn layers_in(cwd: &Path) -> Result<Vec<(String, ConfigFile)>> {
+        let read = |path: &Path| -> Result<Option<ConfigFile>> {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            Ok(ConfigFile::read(path)?.map(|file| file.relative_to(dir)))
+        };
+        let mut layers = vec![("defaults".to_string(), ConfigFile::defaults())];
+        if let Some(path) = Self::user_path() {
+            if let Some(file) = read(&path)? {
+                layers.push((path.display().to_string(), file));
+            }
+        }
+        if let Some(database) = Self::marker_database(cwd) {
+            let marker = ConfigFile {
+                database: Some(database.display().to_string()),
+                ..ConfigFile::default()
+            };
+            layers.push((format!("{} marker", MARKER), marker));
+        }
+        if let Some(path) = Self::project_path(cwd) {
+            if let Some(file) = read(&path)? {
+                layers.push((path.display().to_string(), file));
+            }
+        }
+        let env = ConfigFile::from_env(|name| std::env::var(name).ok())?;
+        layers.push(("environment".to_string(), env));
+        Ok(layers)
+    }
+
+    /// Local database opted into by the nearest `.todo` marker in `start` or
+    /// one of its parents: `.todo/todo.db` when the marker is a directory,
+    /// otherwise `todo.db` next to the marker file
+    pub fn marker_database(start: &Path) -> Option<PathBuf> {
+        start.ancestors().find_map(|dir| {
+            let marker = dir.join(MARKER);
+            if marker.is_dir() {
+                Some(marker.join("todo.db"))
+            } else if marker.is_file() {
+                Some(dir.join("todo.db"))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Parse raw settings, using defaults for anything unset
+    pub fn resolve(file: &ConfigFile) -> Result<Self> {
+        let defaults = Self::default();
+        fn parse<T>(key: &str, value: &Option<String>, default: T) -> Result<T>
+        where
+            T: FromStr,
+            T::Err: fmt::Display,
+        {
+            match value {
+                Some(value) => value
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("invalid config value for {}: {}", key, e)),
+                None => Ok(default),
+            }
+        }
+        Ok(Self {
+            database_url: file.database.clone().unwrap_or(defaults.database_url),
+            filter: parse("filter", &file.filter, defaults.filter)?,
+            sort: parse("sort", &file.sort, defaults.sort)?,
+            theme: parse("theme", &file.theme, defaults.theme)?,
+            keymap: Keymap::from_pairs(
+                file.keymap
+                    .iter()
+                    .map(|(key, target)| (key.as_str(), target.as_str())),
+            )?,
+            output: parse("output", &file.output, defaults.output)?,
+            identity: file.identity.clone(),
+            auto_archive: file
+                .auto_archive
+                .as_deref()
+                .map(parse_age)
+                .transpose()
+                .map_err(|e| anyhow::anyhow!("invalid config value for auto_archive: {}", e))?,
+            database_options: DatabaseOptions {
+                wal: parse("wal", &file.wal, defaults.database_options.wal)?,
+                busy_timeout: std::time::Duration::from_millis(parse(
+                    "busy_timeout",
+                    &file.busy_timeout,
+                    defaults.database_options.busy_timeout.as_millis() as u64,
+                )?),
+                busy_retries: parse(
+                    "busy_retries",
+                    &file.busy_retries,
+                    defaults.database_options.busy_retries,
+                )?,
+                key: file
+                    .key_file
+                    .as_ref()
+                    .map(|path| Key::File(PathBuf::from(path))),
+            },
+            transitions: parse("transitions", &file.transitions, defaults.transitions)?,
+            wip_limits: file
+                .wip_limits
+                .iter()
+                .map(|(status, &limit)| {
+                    let status = status.parse().map_err(|e| {
+                        anyhow::anyhow!("invalid config key wip_limits.{}: {}", status, e)
+                    })?;
+                    Ok((status, limit))
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Path of the user config file
+    pub fn user_path() -> Option<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("todo").join("config.toml"))
+    }
+
+    /// Nearest project config file in `start` or one of its parents
+    pub fn project_path(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+    }
+}
+
+/// Default database location, `$XDG_DATA_HOME/todo/todo.db`
+pub fn default_database_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".local")
+            .join("share"),
+    };
+    Some(base.join("todo").join("todo.db"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence() {
+        let mut merged: ConfigFile = toml::from_str(
+            r#"
+            database = "user.db"
+            sort = "title"
+            theme = "light"
+            wal = false
+            busy_timeout = 250
+            [keymap]
+            j = "down"
+            "#,
+        )
+        .unwrap();
+        merged.merge(toml::from_str(r#"database = "project.db""#).unwrap());
+        merged.merge(
+            ConfigFile::from_env(|name| match name {
+                "TODO_SORT" => Some("due,created:desc".to_string()),
+                "TODO_KEYMAP" => Some("k=up".to_string()),
+                "TODO_KEY_FILE" => Some("todo.key".to_string()),
+                _ => None,
+            })
+            .unwrap(),
+        );
+
+        let config = Config::resolve(&merged).unwrap();
+        assert_eq!(config.database_url, "project.db");
+        assert_eq!(config.sort.to_string(), "due:asc,created:desc");
+        assert_eq!(config.theme, Theme::Light);
+        assert_eq!(config.output, OutputFormat::Text);
+        assert!(!config.database_options.wal);
+        assert_eq!(
+            config.database_options.busy_timeout,
+            std::time::Duration::from_millis(250)
+        );
+        assert_eq!(
+            config.database_options.key,
+            Some(Key::File(PathBuf::from("todo.key")))
+        );
+        assert_eq!(
+            merged.get("keymap").unwrap().as_deref(),
+            Some("j=down,k=up")
+        );
+    }
+
+    #[test]
+    fn test_workflow_layering() {
+        let mut merged: ConfigFile = toml::from_str(
+            r#"
+            transitions = "todo:in_progress;in_progress:done"
+            [wip_limits]
+            in_progress = 3
+            blocked = 2
+            "#,
+        )
+        .unwrap();
+        merged.merge(toml::from_str("[wip_limits]\nin_progress = 5").unwrap());
+        merged.merge(
+            ConfigFile::from_env(|name| match name {
+                "TODO_WIP_LIMITS" => Some("waiting=1".to_string()),
+                _ => None,
+            })
+            .unwrap(),
+        );
+
+        let config = Config::resolve(&merged).unwrap();
+        assert!(config.transitions.allows(Status::Todo, Status::InProgress));
+        assert!(!config.transitions.allows(Status::Todo, Status::Done));
+        let mut limits = config.wip_limits;
+        limits.sort_by_key(|&(status, _)| status.as_str());
+        assert_eq!(
+            limits,
+            vec![
+                (Status::Blocked, 2),
+                (Status::InProgress, 5),
+                (Status::Waiting, 1)
+            ]
+        );
+        assert_eq!(
+            merged.get("wip_limits").unwrap().as_deref(),
+            Some("blocked=2,in_progress=5,waiting=1")
+        );
+
+        let mut file = ConfigFile::default();
+        file.set("wip_limits.done", "10").unwrap();
+        assert_eq!(file.get("wip_limits.done").unwrap().as_deref(), Some("10"));
+        assert!(file.set("wip_limits.later", "1").is_err());
+        assert!(file.set("wip_limits.done", "many").is_err());
+        assert!(file.set("transitions", "todo").is_err());
+        assert!(ConfigFile::from_env(|name| match name {
+            "TODO_WIP_LIMITS" => Some("blocked".to_string()),
+            _ => None,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_project_paths_from_subdirectory() {
+        let root = std::env::temp_dir().join(format!("todo-project-{}", uuid::Uuid::new_v4()));
+        let nested = root.join("sub").join("dir");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            root.join(PROJECT_FILE),
+            "database = \"proj.db\"\nkey_file = \"keys/todo.key\"\n",
+        )
+        .unwrap();
+
+        let layers = Config::layers_in(&nested).unwrap();
+        let project = root.join(PROJECT_FILE).display().to_string();
+        let (_, file) = layers.iter().find(|(name, _)| *name == project).unwrap();
+        assert_eq!(
+            file.database.as_deref(),
+            Some(root.join("proj.db").display().to_string().as_str())
+        );
+        assert_eq!(
+            file.key_file.as_deref(),
+            Some(
+                root.join("keys")
+                    .join("todo.key")
+                    .display()
+                    .to_string()
+                    .as_str()
+            )
+        );
+
+        let file = ConfigFile {
+            database: Some(":memory:".to_string()),
+            key_file: Some("/etc/todo.key".to_string()),
+            ..ConfigFile::default()
+        };
+        assert_eq!(file.clone().relative_to(&root), file);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_marker_database() {
+        let root = std::env::temp_dir().join(format!("todo-marker-{}", uuid::Uuid::new_v4()));
+        let nested = root.join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(Config::marker_database(&nested), None);
+
+        std::fs::write(root.join(MARKER), "").unwrap();
+        assert_eq!(Config::marker_database(&nested), Some(root.join("todo.db")));
+
+        std::fs::create_dir(root.join("a").join(MARKER)).unwrap();
+        assert_eq!(
+            Config::marker_database(&nested),
+            Some(root.join("a").join(MARKER).join("todo.db"))
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_set_validates() {
+        let mut file = ConfigFile::default();
+        file.set("output", "json").unwrap();
+        file.set("keymap.x", "d").unwrap();
+        assert_eq!(file.get("output").unwrap().as_deref(), Some("json"));
+        assert_eq!(file.get("keymap.x").unwrap().as_deref(), Some("d"));
+        assert!(file.set("theme", "neon").is_err());
+        assert!(file.set("colour", "red").is_err());
+        assert!(file.set("busy_retries", "-1").is_err());
+        assert_eq!(file.get("theme").unwrap(), None);
+        assert!(toml::from_str::<ConfigFile>("colour = \"red\"").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! TUI settings as plain values, so reading the configuration doesn't depend
+//! on the terminal libraries; the TUI maps them to colours and key codes
+
+use crate::Result;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Todos the TUI lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    All,
+    Completed,
+    Pending,
+    /// Finished todos moved out of the other filters
+    Archived,
+}
+
+impl FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "all" => Ok(Filter::All),
+            "pending" | "open" => Ok(Filter::Pending),
+            "completed" | "done" | "finished" => Ok(Filter::Completed),
+            "archived" => Ok(Filter::Archived),
+            other => anyhow::bail!(
+                "unknown filter: {} (expected all, pending, completed or archived)",
+                other
+            ),
+        }
+    }
+}
+
+/// Colour scheme of the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// Light text on a dark terminal background
+    #[default]
+    Dark,
+    /// Dark text on a light terminal background
+    Light,
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        })
+    }
+}
+
+impl FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "dark" | "default" => Ok(Theme::Dark),
+            "light" => Ok(Theme::Light),
+            other => anyhow::bail!("unknown theme: {} (expected dark or light)", other),
+        }
+    }
+}
+
+/// A key as named in key bindings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyName {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Esc,
+    Tab,
+    Backspace,
+}
+
+impl FromStr for KeyName {
+    type Err = anyhow::Error;
+
+    /// Parse a single character or one of `up`, `down`, `left`, `right`,
+    /// `enter`, `esc`, `space`, `tab`, `backspace`
+    fn from_str(name: &str) -> Result<Self> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(KeyName::Char(c));
+        }
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "up" => KeyName::Up,
+            "down" => KeyName::Down,
+            "left" => KeyName::Left,
+            "right" => KeyName::Right,
+            "enter" => KeyName::Enter,
+            "esc" | "escape" => KeyName::Esc,
+            "space" => KeyName::Char(' '),
+            "tab" => KeyName::Tab,
+            "backspace" => KeyName::Backspace,
+            other => anyhow::bail!("unknown key: {}", other),
+        })
+    }
+}
+
+/// Extra key bindings: each key acts like the built-in key it is bound to,
+/// e.g. binding `j` to `down` makes `j` move the cursor like the down arrow
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<KeyName, KeyName>,
+}
+
+impl Keymap {
+    /// Build a keymap from `(key, built-in key)` pairs, e.g. `("j", "down")`
+    pub fn from_pairs<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self> {
+        let mut bindings = HashMap::new();
+        for (key, target) in pairs {
+            bindings.insert(key.parse()?, target.parse()?);
+        }
+        Ok(Self { bindings })
+    }
+
+    /// The built-in key `key` is bound to, if any
+    pub fn get(&self, key: KeyName) -> Option<KeyName> {
+        self.bindings.get(&key).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap_from_pairs() {
+        let keymap = Keymap::from_pairs([("j", "down"), ("x", "Space")]).unwrap();
+        assert_eq!(keymap.get(KeyName::Char('j')), Some(KeyName::Down));
+        assert_eq!(keymap.get(KeyName::Char('x')), Some(KeyName::Char(' ')));
+        assert_eq!(keymap.get(KeyName::Char('k')), None);
+        assert!(Keymap::from_pairs([("j", "sideways")]).is_err());
+    }
+}
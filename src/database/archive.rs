@@ -0,0 +1,139 @@
+use super::{audit, events, Sort, TodoDatabase};
+use crate::models::{AuditAction, EventKind, Todo};
+use crate::Result;
+use chrono::{NaiveDate, Utc};
+use sqlx::SqliteConnection;
+
+/// Finished todos eligible for archiving
+const CLOSED: &str = "status IN ('done', 'cancelled')";
+
+impl TodoDatabase {
+    /// Archived todos, in the given order
+    pub async fn list_archived(&self, sort: &Sort) -> Result<Vec<Todo>> {
+        let sql = format!(
+            "SELECT * FROM todos WHERE deleted_at IS NULL AND archived_at IS NOT NULL {}",
+            sort.to_sql()
+        );
+        Ok(sqlx::query_as::<_, Todo>(&sql)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    /// Up to `limit` archived todos in `sort` order, skipping the first `offset`
+    pub async fn list_archived_window(
+        &self,
+        sort: &Sort,
+        offset: u64,
+        limit: u32,
+    ) -> Result<Vec<Todo>> {
+        let sql = format!(
+            "SELECT * FROM todos WHERE deleted_at IS NULL AND archived_at IS NOT NULL {} LIMIT ? OFFSET ?",
+            sort.to_sql()
+        );
+        Ok(sqlx::query_as::<_, Todo>(&sql)
+            .bind(limit)
+            .bind(offset as i64)
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    /// Archive a done or cancelled todo, returning whether it was archived
+    pub async fn archive_todo(&self, id: &str) -> Result<bool> {
+        Ok(self.set_archived(&[id.to_string()], true).await? > 0)
+    }
+
+    /// Take a todo back out of the archive, returning whether it was archived
+    pub async fn unarchive_todo(&self, id: &str) -> Result<bool> {
+        Ok(self.set_archived(&[id.to_string()], false).await? > 0)
+    }
+
+    /// Archive every done or cancelled todo, or only those finished before
+    /// `completed_before`, returning how many were archived
+    ///
+    /// Cancelled todos have no completion time, so their last update counts
+    /// instead. Days are local, like `completed_before`.
+    pub async fn archive_completed(&self, completed_before: Option<NaiveDate>) -> Result<u64> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            let mut sql = format!(
+                "SELECT id FROM todos WHERE {} AND archived_at IS NULL AND deleted_at IS NULL",
+                CLOSED
+            );
+            if completed_before.is_some() {
+                sql.push_str(" AND date(COALESCE(completed_at, updated_at), 'localtime') < ?");
+            }
+            let mut query = sqlx::query_scalar(&sql);
+            if let Some(date) = completed_before {
+                query = query.bind(date);
+            }
+            let ids: Vec<String> = query.fetch_all(&mut *tx).await?;
+            let changed = self.set_archived_in(&mut tx, &ids, true).await?;
+            tx.commit().await?;
+            Ok(changed)
+        })
+        .await
+    }
+
+    async fn set_archived(&self, ids: &[String], archived: bool) -> Result<u64> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            let changed = self.set_archived_in(&mut tx, ids, archived).await?;
+            tx.commit().await?;
+            Ok(changed)
+        })
+        .await
+    }
+
+    /// Archive or unarchive `ids`; archiving skips trashed todos and todos
+    /// that are not finished, checked as they are written
+    async fn set_archived_in(
+        &self,
+        conn: &mut SqliteConnection,
+        ids: &[String],
+        archived: bool,
+    ) -> Result<u64> {
+        let now = Utc::now();
+        let (sql, kind, action) = if archived {
+            (
+                format!(
+                    "UPDATE todos SET archived_at = ? WHERE id = ? AND {} \
+                     AND archived_at IS NULL AND deleted_at IS NULL",
+                    CLOSED
+                ),
+                EventKind::Archived,
+                AuditAction::Archive,
+            )
+        } else {
+            (
+                "UPDATE todos SET archived_at = NULL WHERE id = ? AND archived_at IS NOT NULL"
+                    .to_string(),
+                EventKind::Unarchived,
+                AuditAction::Unarchive,
+            )
+        };
+        let mut changed = 0;
+        for id in ids {
+            let before = audit::fetch(conn, id).await?;
+            let mut query = sqlx::query(&sql);
+            if archived {
+                query = query.bind(now.to_rfc3339());
+            }
+            let rows = query.bind(id).execute(&mut *conn).await?.rows_affected();
+            if rows > 0 {
+                events::record_lifecycle(conn, id, kind, now).await?;
+                let after = audit::fetch(conn, id).await?;
+                audit::record(
+                    conn,
+                    &self.actor,
+                    action,
+                    id,
+                    before.as_ref(),
+                    after.as_ref(),
+                )
+                .await?;
+                changed += rows;
+            }
+        }
+        Ok(changed)
+    }
+}
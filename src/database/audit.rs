@@ -0,0 +1,120 @@
+use super::TodoDatabase;
+use crate::models::{AuditAction, AuditEntry, Todo};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use sqlx::SqliteConnection;
+
+/// Identity recorded when none is configured: the OS user, if known
+pub fn default_actor() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+impl TodoDatabase {
+    /// Audit log entries, oldest first, optionally only those made at or after
+    /// `since` and by `actor`
+    pub async fn audit_log(
+        &self,
+        since: Option<DateTime<Utc>>,
+        actor: Option<&str>,
+    ) -> Result<Vec<AuditEntry>> {
+        let mut sql = "SELECT * FROM audit_log WHERE 1 = 1".to_string();
+        if since.is_some() {
+            sql.push_str(" AND julianday(created_at) >= julianday(?)");
+        }
+        if actor.is_some() {
+            sql.push_str(" AND actor = ?");
+        }
+        sql.push_str(" ORDER BY created_at, id");
+
+        let mut query = sqlx::query_as::<_, AuditEntry>(&sql);
+        if let Some(since) = since {
+            query = query.bind(since.to_rfc3339());
+        }
+        if let Some(actor) = actor {
+            query = query.bind(actor);
+        }
+        Ok(query.fetch_all(&self.pool).await?)
+    }
+}
+
+/// The stored row of a todo, including trashed and archived ones
+pub(super) async fn fetch(conn: &mut SqliteConnection, id: &str) -> Result<Option<Todo>> {
+    let todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = ?")
+        .bind(id)
+        .fetch_optional(conn)
+        .await?;
+    Ok(todo)
+}
+
+/// Append an audit entry for a change of the todo from `before` to `after`
+pub(super) async fn record(
+    conn: &mut SqliteConnection,
+    actor: &str,
+    action: AuditAction,
+    todo_id: &str,
+    before: Option<&Todo>,
+    after: Option<&Todo>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO audit_log (actor, action, todo_id, diff, created_at) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(actor)
+    .bind(action)
+    .bind(todo_id)
+    .bind(diff(before, after)?.to_string())
+    .bind(Utc::now().to_rfc3339())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// `{"before": ..., "after": ...}` with only the fields that differ, or the
+/// whole todo on the side that exists when the other one doesn't
+fn diff(before: Option<&Todo>, after: Option<&Todo>) -> Result<Value> {
+    let object = |todo: Option<&Todo>| -> Result<Option<Map<String, Value>>> {
+        Ok(match todo.map(serde_json::to_value).transpose()? {
+            Some(Value::Object(map)) => Some(map),
+            _ => None,
+        })
+    };
+    let (before, after) = match (object(before)?, object(after)?) {
+        (Some(mut before), Some(mut after)) => {
+            before.retain(|key, value| after.get(key) != Some(value));
+            after.retain(|key, _| before.contains_key(key));
+            (Value::Object(before), Value::Object(after))
+        }
+        (before, after) => (
+            before.map_or(Value::Null, Value::Object),
+            after.map_or(Value::Null, Value::Object),
+        ),
+    };
+    let mut diff = Map::new();
+    diff.insert("before".to_string(), before);
+    diff.insert("after".to_string(), after);
+    Ok(Value::Object(diff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_keeps_changed_fields() {
+        let before = Todo::new("Before".to_string(), None);
+        let mut after = before.clone();
+        after.update(Some("After".to_string()), None);
+
+        let changes = diff(Some(&before), Some(&after)).unwrap();
+        assert_eq!(changes["before"]["title"], "Before");
+        assert_eq!(changes["after"]["title"], "After");
+        assert!(changes["after"].get("updated_at").is_some());
+        assert!(changes["after"].get("id").is_none());
+
+        let changes = diff(None, Some(&after)).unwrap();
+        assert!(changes["before"].is_null());
+        assert_eq!(changes["after"]["id"], after.id.as_str());
+    }
+}
@@ -0,0 +1,261 @@
+//! Copies of the database file: manual backups and restores, automatic
+//! backups before migrations, and an integrity check
+
+use super::encryption::{key_error, Key};
+use super::{TodoDatabase, SCHEMA_VERSION};
+use crate::models::Status;
+use crate::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Connection, SqliteConnection};
+use std::path::{Path, PathBuf};
+
+/// Number of automatic backups kept from before migrations
+pub const MIGRATION_BACKUPS: usize = 5;
+
+/// Directory holding the automatic backups of the database at `database`,
+/// e.g. `todo.db.backups` next to `todo.db`
+pub fn backup_dir(database: &Path) -> PathBuf {
+    let mut name = database.file_name().unwrap_or_default().to_os_string();
+    name.push(".backups");
+    database.with_file_name(name)
+}
+
+/// Write a consistent copy of the database open on `conn` to `path`, which
+/// must not exist yet
+async fn vacuum_into(conn: &mut SqliteConnection, path: &Path) -> Result<()> {
+    if path.exists() {
+        anyhow::bail!("{} already exists", path.display());
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy().as_ref())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Move the copy at `staging` over the database file at `database`, which is
+/// readable with `key`
+///
+/// The database's write-ahead log is checkpointed into the old file first,
+/// and the move refused while another connection keeps the log open: the new
+/// file would otherwise pick up a log that belongs to the old one.
+pub(super) async fn replace_file(database: &Path, staging: &Path, key: Option<&Key>) -> Result<()> {
+    if database.exists() {
+        let name = database.display().to_string();
+        let mut options = SqliteConnectOptions::new().filename(database);
+        if let Some(key) = key {
+            options = options.pragma("key", key.to_sql()?);
+        }
+        let mut conn = SqliteConnection::connect_with(&options)
+            .await
+            .map_err(|e| key_error(e, &name, key))?;
+        let (busy, _, _): (i64, i64, i64) = sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)")
+            .fetch_one(&mut conn)
+            .await
+            .map_err(|e| key_error(e, &name, key))?;
+        // The last connection to close removes the log
+        conn.close().await?;
+        let mut wal = database.as_os_str().to_os_string();
+        wal.push("-wal");
+        if busy != 0 || Path::new(&wal).exists() {
+            anyhow::bail!(
+                "{} is in use; close other todo processes first",
+                database.display()
+            );
+        }
+    }
+    std::fs::rename(staging, database)?;
+    Ok(())
+}
+
+/// Problems reported by `PRAGMA integrity_check`, empty if there are none
+async fn integrity_problems(conn: &mut SqliteConnection) -> Result<Vec<String>> {
+    let rows: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(conn)
+        .await?;
+    Ok(rows.into_iter().filter(|row| row != "ok").collect())
+}
+
+impl TodoDatabase {
+    /// Write a consistent copy of the database to `path`, which must not
+    /// exist yet; other processes may keep using the database meanwhile
+    pub async fn backup(&self, path: &Path) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        vacuum_into(&mut conn, path).await
+    }
+
+    /// Back up the file database at `database` into its [`backup_dir`] before
+    /// migrating it from `version`, keeping the newest [`MIGRATION_BACKUPS`]
+    pub(super) async fn backup_before_migration(
+        &self,
+        database: &Path,
+        version: i64,
+    ) -> Result<()> {
+        let dir = backup_dir(database);
+        let stem = database
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let name = format!(
+            "{}-v{}-{}.db",
+            stem,
+            version,
+            Utc::now().format("%Y%m%dT%H%M%S%.3f")
+        );
+        self.backup(&dir.join(name)).await?;
+
+        // Names sort by time, as the timestamp follows a fixed prefix
+        let prefix = format!("{}-v", stem);
+        let mut backups: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".db"))
+            })
+            .collect();
+        backups.sort_by_key(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.rsplit('-').next())
+                .map(str::to_string)
+        });
+        let excess = backups.len().saturating_sub(MIGRATION_BACKUPS);
+        for old in &backups[..excess] {
+            std::fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the database file at `database` with the backup at `backup`,
+    /// returning the backup's schema version
+    ///
+    /// The backup must pass an integrity check and must not come from a newer
+    /// version of the app; an older schema, down to the baseline one at
+    /// version 0, is migrated the next time the database is opened. A backup of an encrypted database needs its `key`,
+    /// which must also open the database it replaces. Refused while other
+    /// processes have the database open.
+    pub async fn restore(database: &Path, backup: &Path, key: Option<&Key>) -> Result<i64> {
+        if !backup.is_file() {
+            anyhow::bail!("no backup at {}", backup.display());
+        }
+        let name = backup.display().to_string();
+        let mut options = SqliteConnectOptions::new().filename(backup).read_only(true);
+        if let Some(key) = key {
+            options = options.pragma("key", key.to_sql()?);
+        }
+        let mut conn = SqliteConnection::connect_with(&options)
+            .await
+            .map_err(|e| key_error(e, &name, key))?;
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+            .fetch_one(&mut conn)
+            .await
+            .map_err(|e| key_error(e, &name, key))?;
+        if version > SCHEMA_VERSION {
+            anyhow::bail!(
+                "backup schema version {} is newer than supported version {}",
+                version,
+                SCHEMA_VERSION
+            );
+        }
+        // Databases from before schema versions were recorded are at version 0
+        let todos: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'todos'",
+        )
+        .fetch_one(&mut conn)
+        .await?;
+        if todos == 0 {
+            anyhow::bail!("{} is not a todo database", backup.display());
+        }
+        let problems = integrity_problems(&mut conn).await?;
+        if !problems.is_empty() {
+            anyhow::bail!("backup failed the integrity check: {}", problems.join("; "));
+        }
+
+        // Copy next to the database first, so a failure leaves it untouched
+        let mut staging = database.as_os_str().to_os_string();
+        staging.push(".restore");
+        let staging = PathBuf::from(staging);
+        let _ = std::fs::remove_file(&staging);
+        vacuum_into(&mut conn, &staging).await?;
+        conn.close().await?;
+        if let Err(e) = replace_file(database, &staging, key).await {
+            let _ = std::fs::remove_file(&staging);
+            return Err(e);
+        }
+        Ok(version)
+    }
+
+    /// Run SQLite's integrity check and validate the stored values that the
+    /// app parses, returning a description of each problem found
+    pub async fn check(&self) -> Result<Vec<String>> {
+        let mut conn = self.pool.acquire().await?;
+        let mut problems = integrity_problems(&mut conn).await?;
+
+        type Row = (
+            String,
+            String,
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+        );
+        let rows: Vec<Row> = sqlx::query_as(
+            "SELECT id, status, created_at, updated_at, completed_at, deleted_at, archived_at, due_date FROM todos",
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        for (id, status, created, updated, completed, deleted, archived, due) in rows {
+            if uuid::Uuid::parse_str(&id).is_err() {
+                problems.push(format!("todo {}: id is not a UUID", id));
+            }
+            if !Status::ALL.iter().any(|s| s.as_str() == status) {
+                problems.push(format!("todo {}: unknown status {:?}", id, status));
+            }
+            let timestamps = [
+                ("created_at", Some(created)),
+                ("updated_at", Some(updated)),
+                ("completed_at", completed),
+                ("deleted_at", deleted),
+                ("archived_at", archived),
+            ];
+            for (column, value) in timestamps {
+                if let Some(value) = value {
+                    if DateTime::parse_from_rfc3339(&value).is_err() {
+                        problems.push(format!("todo {}: invalid {} {:?}", id, column, value));
+                    }
+                }
+            }
+            if let Some(due) = due {
+                if due.parse::<NaiveDate>().is_err() {
+                    problems.push(format!("todo {}: invalid due_date {:?}", id, due));
+                }
+            }
+        }
+
+        let events: Vec<(i64, String)> = sqlx::query_as("SELECT id, created_at FROM todo_events")
+            .fetch_all(&mut *conn)
+            .await?;
+        let audit: Vec<(i64, String)> = sqlx::query_as("SELECT id, created_at FROM audit_log")
+            .fetch_all(&mut *conn)
+            .await?;
+        for (table, rows) in [("todo_events", events), ("audit_log", audit)] {
+            for (id, created) in rows {
+                if DateTime::parse_from_rfc3339(&created).is_err() {
+                    problems.push(format!(
+                        "{} {}: invalid created_at {:?}",
+                        table, id, created
+                    ));
+                }
+            }
+        }
+        Ok(problems)
+    }
+}
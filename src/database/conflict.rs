@@ -0,0 +1,27 @@
+use crate::models::Todo;
+use std::fmt;
+
+/// An update was based on an outdated version of the todo, i.e. someone else
+/// changed it since it was read
+///
+/// Returned, wrapped in the usual error, by [`super::TodoDatabase::update_todo`]
+/// and the other checked updates; find it with `error.downcast_ref::<Conflict>()`.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    /// The todo as it is stored now
+    pub current: Todo,
+    /// The version the rejected update was based on
+    pub expected: i64,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "todo '{}' was changed by someone else (version {}, expected {}); reload it and try again",
+            self.current.title, self.current.version, self.expected
+        )
+    }
+}
+
+impl std::error::Error for Conflict {}
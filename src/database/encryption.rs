@@ -0,0 +1,149 @@
+//! Encryption at rest with SQLCipher: the whole file, including the history
+//! and audit log, is encrypted with a key derived from a passphrase or key file
+//! (SQLCipher runs PBKDF2-HMAC-SHA512 over it)
+
+use super::backup::replace_file;
+use super::TodoDatabase;
+use crate::Result;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Connection, SqliteConnection};
+use std::path::{Path, PathBuf};
+
+/// SQLite's result code for a file that is not a database, which is also what
+/// SQLCipher reports for a wrong or missing key
+const NOT_A_DATABASE: &str = "26";
+
+/// Secret an encrypted database is opened with
+#[derive(Clone, PartialEq, Eq)]
+pub enum Key {
+    Passphrase(String),
+    /// File whose contents are the secret; any bytes will do, e.g. the output
+    /// of `head -c 32 /dev/urandom`
+    File(PathBuf),
+}
+
+// Keep passphrases out of logs and error messages
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Passphrase(_) => f.write_str("Passphrase(..)"),
+            Key::File(path) => f.debug_tuple("File").field(path).finish(),
+        }
+    }
+}
+
+impl Key {
+    /// The secret as an SQL string literal, for `PRAGMA key` and `ATTACH ... KEY`
+    pub(super) fn to_sql(&self) -> Result<String> {
+        let secret = match self {
+            Key::Passphrase(passphrase) => passphrase.clone(),
+            Key::File(path) => {
+                let bytes = std::fs::read(path).map_err(|e| {
+                    anyhow::anyhow!("cannot read key file {}: {}", path.display(), e)
+                })?;
+                if bytes.is_empty() {
+                    anyhow::bail!("key file {} is empty", path.display());
+                }
+                // Hex keeps binary key files intact as a passphrase
+                bytes.iter().map(|b| format!("{:02x}", b)).collect()
+            }
+        };
+        if secret.is_empty() {
+            anyhow::bail!("the passphrase is empty");
+        }
+        Ok(format!("'{}'", secret.replace('\'', "''")))
+    }
+}
+
+/// Explain a failure to read the database at `database` caused by the key
+pub(super) fn key_error(error: sqlx::Error, database: &str, key: Option<&Key>) -> anyhow::Error {
+    let wrong_key = error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == NOT_A_DATABASE);
+    match (wrong_key, key) {
+        (true, Some(_)) => anyhow::anyhow!(
+            "cannot open {}: wrong key, or the database is not encrypted",
+            database
+        ),
+        (true, None) => anyhow::anyhow!(
+            "cannot open {}: the database is encrypted (set key_file or TODO_PASSPHRASE)",
+            database
+        ),
+        (false, _) => error.into(),
+    }
+}
+
+impl TodoDatabase {
+    /// Encrypt the unencrypted database file at `database` with `key`
+    pub async fn encrypt(database: &Path, key: &Key) -> Result<()> {
+        export(database, None, Some(key)).await
+    }
+
+    /// Store the database file at `database`, encrypted with `key`, unencrypted
+    pub async fn decrypt(database: &Path, key: &Key) -> Result<()> {
+        export(database, Some(key), None).await
+    }
+
+    /// Re-encrypt the database file at `database` from `old` to `new`
+    pub async fn rekey(database: &Path, old: &Key, new: &Key) -> Result<()> {
+        export(database, Some(old), Some(new)).await
+    }
+}
+
+/// Copy the database at `database`, readable with `from`, to a file encrypted
+/// with `to` (unencrypted if `None`) and put the copy in its place
+///
+/// Like a restore, this replaces the file, so it is refused while other
+/// processes have the database open.
+async fn export(database: &Path, from: Option<&Key>, to: Option<&Key>) -> Result<()> {
+    if !database.is_file() {
+        anyhow::bail!("no database at {}", database.display());
+    }
+    let name = database.display().to_string();
+    // Attached databases are opened with the same flags, so this lets ATTACH
+    // create the copy
+    let mut options = SqliteConnectOptions::new()
+        .filename(database)
+        .create_if_missing(true);
+    if let Some(key) = from {
+        options = options.pragma("key", key.to_sql()?);
+    }
+    let mut conn = SqliteConnection::connect_with(&options)
+        .await
+        .map_err(|e| key_error(e, &name, from))?;
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(&mut conn)
+        .await
+        .map_err(|e| key_error(e, &name, from))?;
+
+    let mut staging = database.as_os_str().to_os_string();
+    staging.push(".rekey");
+    let staging = PathBuf::from(staging);
+    let _ = std::fs::remove_file(&staging);
+    let target_key = match to {
+        Some(key) => key.to_sql()?,
+        None => "''".to_string(),
+    };
+    // Written out quoted, as for `PRAGMA key`
+    sqlx::query(&format!("ATTACH DATABASE ? AS target KEY {}", target_key))
+        .bind(staging.to_string_lossy().as_ref())
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("SELECT sqlcipher_export('target')")
+        .execute(&mut conn)
+        .await?;
+    // Not copied by the export; it records which migrations have run
+    sqlx::query(&format!("PRAGMA target.user_version = {}", version))
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("DETACH DATABASE target")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+    if let Err(e) = replace_file(database, &staging, from).await {
+        let _ = std::fs::remove_file(&staging);
+        return Err(e);
+    }
+    Ok(())
+}
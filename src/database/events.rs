@@ -0,0 +1,136 @@
+use super::TodoDatabase;
+use crate::models::{EventKind, Todo, TodoEvent};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqliteConnection;
+
+impl TodoDatabase {
+    /// History of a todo, oldest first. Events outlive the todo itself.
+    pub async fn todo_events(&self, todo_id: &str) -> Result<Vec<TodoEvent>> {
+        let events = sqlx::query_as::<_, TodoEvent>(
+            "SELECT * FROM todo_events WHERE todo_id = ? ORDER BY created_at, id",
+        )
+        .bind(todo_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events)
+    }
+}
+
+async fn record(
+    conn: &mut SqliteConnection,
+    todo_id: &str,
+    kind: EventKind,
+    old_value: Option<String>,
+    new_value: Option<String>,
+    note: Option<&str>,
+    at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO todo_events (todo_id, kind, old_value, new_value, note, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(todo_id)
+    .bind(kind)
+    .bind(old_value)
+    .bind(new_value)
+    .bind(note)
+    .bind(at.to_rfc3339())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Record the creation of `todo`, including its initial status if it is not the default
+pub(super) async fn record_created(conn: &mut SqliteConnection, todo: &Todo) -> Result<()> {
+    record(
+        conn,
+        &todo.id,
+        EventKind::Created,
+        None,
+        Some(todo.title.clone()),
+        None,
+        todo.created_at,
+    )
+    .await?;
+    if todo.status != Default::default() {
+        record(
+            conn,
+            &todo.id,
+            EventKind::Status,
+            None,
+            Some(todo.status.to_string()),
+            todo.status_reason.as_deref(),
+            todo.completed_at.unwrap_or(todo.updated_at),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Record every field that differs between `before` and `after`
+pub(super) async fn record_changes(
+    conn: &mut SqliteConnection,
+    before: &Todo,
+    after: &Todo,
+) -> Result<()> {
+    let at = after.updated_at;
+    if before.status != after.status || before.status_reason != after.status_reason {
+        record(
+            conn,
+            &after.id,
+            EventKind::Status,
+            Some(before.status.to_string()),
+            Some(after.status.to_string()),
+            after.status_reason.as_deref(),
+            at,
+        )
+        .await?;
+    }
+    if before.title != after.title {
+        record(
+            conn,
+            &after.id,
+            EventKind::Renamed,
+            Some(before.title.clone()),
+            Some(after.title.clone()),
+            None,
+            at,
+        )
+        .await?;
+    }
+    if before.description != after.description {
+        record(
+            conn,
+            &after.id,
+            EventKind::Described,
+            before.description.clone(),
+            after.description.clone(),
+            None,
+            at,
+        )
+        .await?;
+    }
+    if before.due_date != after.due_date {
+        record(
+            conn,
+            &after.id,
+            EventKind::Rescheduled,
+            before.due_date.map(|d| d.to_string()),
+            after.due_date.map(|d| d.to_string()),
+            None,
+            at,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Record an event without values, such as moving a todo to the trash or archive
+pub(super) async fn record_lifecycle(
+    conn: &mut SqliteConnection,
+    todo_id: &str,
+    kind: EventKind,
+    at: DateTime<Utc>,
+) -> Result<()> {
+    record(conn, todo_id, kind, None, None, None, at).await
+}
@@ -0,0 +1,142 @@
+use crate::Result;
+use sqlx::SqlitePool;
+
+/// Schema migrations, applied in order. The index of the last applied
+/// migration plus one is stored in SQLite's `user_version` pragma.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS todos (
+        id TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        description TEXT,
+        completed BOOLEAN NOT NULL DEFAULT FALSE,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    )
+    "#,
+    r#"
+    ALTER TABLE todos ADD COLUMN position REAL NOT NULL DEFAULT 0;
+    UPDATE todos SET position = 1024.0 * (
+        SELECT COUNT(*) FROM todos AS newer
+        WHERE newer.created_at > todos.created_at
+            OR (newer.created_at = todos.created_at AND newer.id < todos.id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_todos_position ON todos (position);
+    "#,
+    r#"
+    ALTER TABLE todos ADD COLUMN status TEXT NOT NULL DEFAULT 'todo';
+    UPDATE todos SET status = 'done' WHERE completed;
+    "#,
+    r#"
+    UPDATE todos SET status = 'done' WHERE completed AND status = 'todo';
+    ALTER TABLE todos DROP COLUMN completed;
+    ALTER TABLE todos ADD COLUMN status_reason TEXT;
+    CREATE INDEX IF NOT EXISTS idx_todos_status ON todos (status);
+    "#,
+    r#"
+    ALTER TABLE todos ADD COLUMN due_date TEXT;
+    CREATE INDEX IF NOT EXISTS idx_todos_due_date ON todos (due_date);
+    "#,
+    r#"
+    ALTER TABLE todos ADD COLUMN completed_at TEXT;
+    UPDATE todos SET completed_at = updated_at WHERE status = 'done';
+    CREATE INDEX IF NOT EXISTS idx_todos_completed_at ON todos (completed_at);
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS todo_events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        todo_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        old_value TEXT,
+        new_value TEXT,
+        note TEXT,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_todo_events_todo_id ON todo_events (todo_id, created_at);
+    CREATE TRIGGER IF NOT EXISTS todo_events_no_update BEFORE UPDATE ON todo_events
+    BEGIN
+        SELECT RAISE(ABORT, 'todo_events is append-only');
+    END;
+    CREATE TRIGGER IF NOT EXISTS todo_events_no_delete BEFORE DELETE ON todo_events
+    BEGIN
+        SELECT RAISE(ABORT, 'todo_events is append-only');
+    END;
+    INSERT INTO todo_events (todo_id, kind, new_value, created_at)
+        SELECT id, 'created', title, created_at FROM todos;
+    INSERT INTO todo_events (todo_id, kind, new_value, note, created_at)
+        SELECT id, 'status', status, status_reason, COALESCE(completed_at, updated_at)
+        FROM todos WHERE status != 'todo';
+    "#,
+    r#"
+    ALTER TABLE todos ADD COLUMN deleted_at TEXT;
+    CREATE INDEX IF NOT EXISTS idx_todos_deleted_at ON todos (deleted_at);
+    "#,
+    r#"
+    ALTER TABLE todos ADD COLUMN archived_at TEXT;
+    CREATE INDEX IF NOT EXISTS idx_todos_archived_at ON todos (archived_at);
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS audit_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        actor TEXT NOT NULL,
+        action TEXT NOT NULL,
+        todo_id TEXT NOT NULL,
+        diff TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log (created_at);
+    CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log (actor, created_at);
+    CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+    BEGIN
+        SELECT RAISE(ABORT, 'audit_log is append-only');
+    END;
+    CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+    BEGIN
+        SELECT RAISE(ABORT, 'audit_log is append-only');
+    END;
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS views (
+        name TEXT PRIMARY KEY,
+        query TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    "#,
+    r#"
+    ALTER TABLE todos ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+    "#,
+    r#"
+    ALTER TABLE todos ADD COLUMN priority TEXT;
+    ALTER TABLE todos ADD COLUMN project TEXT;
+    ALTER TABLE todos ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+    CREATE INDEX IF NOT EXISTS idx_todos_project ON todos (project);
+    "#,
+];
+
+/// Schema version after all migrations have run
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// Apply all pending migrations
+pub async fn run(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let current: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(&mut *tx)
+        .await?;
+    if current > SCHEMA_VERSION {
+        anyhow::bail!(
+            "database schema version {} is newer than supported version {}",
+            current,
+            SCHEMA_VERSION
+        );
+    }
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        sqlx::query(migration).execute(&mut *tx).await?;
+        sqlx::query(&format!("PRAGMA user_version = {}", version + 1))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
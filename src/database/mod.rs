@@ -1,16 +1,92 @@
-use crate::models::Todo;
+mod archive;
+mod audit;
+mod backup;
+mod conflict;
+mod encryption;
+mod events;
+mod migrations;
+mod page;
+mod query;
+mod retry;
+mod sort;
+mod stats;
+mod transaction;
+mod trash;
+mod views;
+mod watch;
+
+pub use audit::default_actor;
+pub use backup::{backup_dir, MIGRATION_BACKUPS};
+pub use conflict::Conflict;
+pub use encryption::Key;
+pub use migrations::SCHEMA_VERSION;
+pub use page::PAGE_SIZE;
+pub use query::{Comparison, DateField, Param, Query, Term, TextField};
+pub use sort::{Sort, SortDirection, SortField, SortKey};
+pub use stats::{format_duration, Period, Stats};
+pub use transaction::Transaction;
+
+use crate::models::{AuditAction, EventKind, Status, Todo};
 use crate::Result;
-use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+use chrono::{NaiveDate, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::types::Json;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Distance between neighbouring positions when appending or renumbering
+const POSITION_GAP: f64 = 1024.0;
+
+/// Where to move a todo relative to another one
+#[derive(Debug, Clone, Copy)]
+pub enum Placement<'a> {
+    Before(&'a str),
+    After(&'a str),
+}
+
+/// How to share the database file with other processes, and how to unlock it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseOptions {
+    /// Use write-ahead logging, so readers don't block the writer or each other
+    pub wal: bool,
+    /// How long SQLite waits for another connection's lock before giving up
+    pub busy_timeout: Duration,
+    /// How often a write is started over when the database stays busy
+    pub busy_retries: u32,
+    /// Key of an encrypted database; `None` for an unencrypted one
+    pub key: Option<Key>,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        Self {
+            wal: true,
+            busy_timeout: Duration::from_secs(5),
+            busy_retries: 5,
+            key: None,
+        }
+    }
+}
 
 /// Database service for todo operations
 #[derive(Debug, Clone)]
 pub struct TodoDatabase {
     pool: SqlitePool,
+    /// Identity recorded in the audit log for changes made through this handle
+    actor: String,
+    options: DatabaseOptions,
 }
 
 impl TodoDatabase {
     /// Create a new database connection
     pub async fn new(database_url: &str) -> Result<Self> {
+        Self::open(database_url, DatabaseOptions::default()).await
+    }
+
+    /// Create a new database connection with the given options, opening it
+    /// with `options.key` if it is encrypted
+    pub async fn open(database_url: &str, options: DatabaseOptions) -> Result<Self> {
         // Handle special cases for SQLite URL format
         let url = match database_url {
             ":memory:" => "sqlite::memory:".to_string(),
@@ -24,100 +100,451 @@ impl TodoDatabase {
             }
         };
 
+        let mut connect = SqliteConnectOptions::from_str(&url)?.busy_timeout(options.busy_timeout);
+        // In-memory databases can't use WAL, nor do they need it
+        if options.wal && database_url != ":memory:" {
+            connect = connect.journal_mode(SqliteJournalMode::Wal);
+        }
+        if let Some(key) = &options.key {
+            connect = connect.pragma("key", key.to_sql()?);
+        }
         let pool = SqlitePoolOptions::new()
             .max_connections(5)
-            .connect(&url)
-            .await?;
+            .connect_with(connect)
+            .await
+            .map_err(|e| encryption::key_error(e, database_url, options.key.as_ref()))?;
 
-        let db = Self { pool };
+        let db = Self {
+            pool,
+            actor: default_actor(),
+            options,
+        };
+        // Plain paths are database files; keep a copy from before any migration
+        if !database_url.starts_with("sqlite://") && database_url != ":memory:" {
+            let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+                .fetch_one(&db.pool)
+                .await?;
+            let tables: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master")
+                .fetch_one(&db.pool)
+                .await?;
+            if version < SCHEMA_VERSION && tables > 0 {
+                db.backup_before_migration(std::path::Path::new(database_url), version)
+                    .await?;
+            }
+        }
         db.migrate().await?;
         Ok(db)
     }
 
+    /// Attribute changes to `actor` instead of the OS user
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = actor.into();
+        self
+    }
+
+    /// Close all connections, e.g. before replacing the file
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
     /// Run database migrations
     async fn migrate(&self) -> Result<()> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS todos (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL,
-                description TEXT,
-                completed BOOLEAN NOT NULL DEFAULT FALSE,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+        self.retry(|| migrations::run(&self.pool)).await
     }
 
     /// Get all todos
     pub async fn get_all_todos(&self) -> Result<Vec<Todo>> {
-        let todos = sqlx::query_as::<_, Todo>("SELECT * FROM todos ORDER BY created_at DESC")
+        self.list_todos(&[], &Sort::default()).await
+    }
+
+    /// Get todos with any of the given statuses (all todos if empty), in the given order
+    ///
+    /// Archived todos are left out; see [`TodoDatabase::list_archived`].
+    pub async fn list_todos(&self, statuses: &[Status], sort: &Sort) -> Result<Vec<Todo>> {
+        let sql = if statuses.is_empty() {
+            format!(
+                "SELECT * FROM todos WHERE deleted_at IS NULL AND archived_at IS NULL {}",
+                sort.to_sql()
+            )
+        } else {
+            let placeholders = vec!["?"; statuses.len()].join(", ");
+            format!(
+                "SELECT * FROM todos WHERE deleted_at IS NULL AND archived_at IS NULL \
+                 AND status IN ({}) {}",
+                placeholders,
+                sort.to_sql()
+            )
+        };
+        let mut query = sqlx::query_as::<_, Todo>(&sql);
+        for status in statuses {
+            query = query.bind(*status);
+        }
+        Ok(query.fetch_all(&self.pool).await?)
+    }
+
+    /// Get todos matching a filter query, in the given order
+    ///
+    /// Like [`TodoDatabase::list_todos`], archived todos are left out.
+    pub async fn find_todos(&self, query: &Query, sort: &Sort) -> Result<Vec<Todo>> {
+        let (condition, params) = query.to_sql();
+        let sql = format!(
+            "SELECT * FROM todos WHERE deleted_at IS NULL AND archived_at IS NULL AND {} {}",
+            condition,
+            sort.to_sql()
+        );
+        let todos = page::bind(sqlx::query_as::<_, Todo>(&sql), params)
             .fetch_all(&self.pool)
             .await?;
         Ok(todos)
     }
 
-    /// Get a todo by ID
+    /// Get todos due between `start` and `end` inclusive, earliest first
+    pub async fn get_todos_due_between(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(
+            "SELECT * FROM todos WHERE deleted_at IS NULL AND archived_at IS NULL \
+             AND due_date BETWEEN ? AND ? \
+             ORDER BY due_date, position, id",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(todos)
+    }
+
+    /// Get a todo by ID, unless it is in the trash
     pub async fn get_todo(&self, id: &str) -> Result<Option<Todo>> {
-        let todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = ?")
-            .bind(id)
-            .fetch_optional(&self.pool)
-            .await?;
+        let todo =
+            sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = ? AND deleted_at IS NULL")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
         Ok(todo)
     }
 
     /// Create a new todo
+    ///
+    /// Todos without a position are appended after the last todo in manual order.
     pub async fn create_todo(&self, todo: &Todo) -> Result<()> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            self.create_in(&mut tx, todo).await?;
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Create several todos in a single transaction
+    pub async fn create_many(&self, todos: &[Todo]) -> Result<()> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            for todo in todos {
+                self.create_in(&mut tx, todo).await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Insert `todo` and record its creation
+    async fn create_in(&self, conn: &mut SqliteConnection, todo: &Todo) -> Result<()> {
         sqlx::query(
-            "INSERT INTO todos (id, title, description, completed, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)"
+            "INSERT INTO todos (id, title, description, status, status_reason, priority, project, tags, due_date, completed_at, created_at, updated_at, position) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, (SELECT COALESCE(MAX(position), 0) FROM todos) + ?))"
         )
         .bind(&todo.id)
         .bind(&todo.title)
         .bind(&todo.description)
-        .bind(todo.completed)
+        .bind(todo.status)
+        .bind(&todo.status_reason)
+        .bind(todo.priority)
+        .bind(&todo.project)
+        .bind(Json(&todo.tags))
+        .bind(todo.due_date)
+        .bind(todo.completed_at.map(|t| t.to_rfc3339()))
         .bind(todo.created_at.to_rfc3339())
         .bind(todo.updated_at.to_rfc3339())
-        .execute(&self.pool)
+        .bind(todo.position)
+        .bind(POSITION_GAP)
+        .execute(&mut *conn)
+        .await?;
+        events::record_created(conn, todo).await?;
+        let created = audit::fetch(conn, &todo.id).await?;
+        audit::record(
+            conn,
+            &self.actor,
+            AuditAction::Create,
+            &todo.id,
+            None,
+            created.as_ref(),
+        )
         .await?;
         Ok(())
     }
 
-    /// Update a todo
+    /// Move a todo before or after another todo in manual order
+    ///
+    /// Only the moved row is rewritten, taking the midpoint between its new
+    /// neighbours. When the gap between neighbours becomes too small to split,
+    /// all positions are renumbered first.
+    pub async fn move_todo(&self, id: &str, placement: Placement<'_>) -> Result<()> {
+        self.retry(|| async move {
+            let target_id = match placement {
+                Placement::Before(target) | Placement::After(target) => target,
+            };
+            if target_id == id {
+                anyhow::bail!("cannot move a todo relative to itself");
+            }
+
+            let mut tx = self.pool.begin().await?;
+            let exists: Option<f64> =
+                sqlx::query_scalar("SELECT position FROM todos WHERE id = ? AND deleted_at IS NULL")
+                    .bind(id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            if exists.is_none() {
+                anyhow::bail!("todo not found: {}", id);
+            }
+
+            for _ in 0..2 {
+                let target: f64 = sqlx::query_scalar(
+                    "SELECT position FROM todos WHERE id = ? AND deleted_at IS NULL",
+                )
+                .bind(target_id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("todo not found: {}", target_id))?;
+
+                let (neighbour_sql, fallback) = match placement {
+                    Placement::Before(_) => (
+                        "SELECT MAX(position) FROM todos WHERE position < ? AND id != ? AND deleted_at IS NULL",
+                        target - POSITION_GAP,
+                    ),
+                    Placement::After(_) => (
+                        "SELECT MIN(position) FROM todos WHERE position > ? AND id != ? AND deleted_at IS NULL",
+                        target + POSITION_GAP,
+                    ),
+                };
+                let neighbour: Option<f64> = sqlx::query_scalar(neighbour_sql)
+                    .bind(target)
+                    .bind(id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                let position = neighbour.map_or(fallback, |n| (n + target) / 2.0);
+
+                let splittable = match neighbour {
+                    Some(n) => position > n.min(target) && position < n.max(target),
+                    None => true,
+                };
+                if splittable {
+                    let before = audit::fetch(&mut tx, id).await?;
+                    sqlx::query("UPDATE todos SET position = ? WHERE id = ?")
+                        .bind(position)
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                    let after = audit::fetch(&mut tx, id).await?;
+                    audit::record(
+                        &mut tx,
+                        &self.actor,
+                        AuditAction::Move,
+                        id,
+                        before.as_ref(),
+                        after.as_ref(),
+                    )
+                    .await?;
+                    tx.commit().await?;
+                    return Ok(());
+                }
+
+                Self::renumber_positions(&mut tx).await?;
+            }
+            anyhow::bail!("could not find a free position for todo {}", id)
+        })
+        .await
+    }
+
+    /// Spread all positions out evenly, keeping their current order
+    async fn renumber_positions(tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>) -> Result<()> {
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM todos ORDER BY position, id")
+            .fetch_all(&mut **tx)
+            .await?;
+        for (index, id) in ids.iter().enumerate() {
+            sqlx::query("UPDATE todos SET position = ? WHERE id = ?")
+                .bind((index as f64 + 1.0) * POSITION_GAP)
+                .bind(id)
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Update a todo, provided it is still at `todo.version`
+    ///
+    /// Fails with a [`Conflict`] if someone else updated it in the meantime.
+    /// The stored version becomes `todo.version + 1`.
     pub async fn update_todo(&self, todo: &Todo) -> Result<()> {
-        sqlx::query(
-            "UPDATE todos SET title = ?, description = ?, completed = ?, updated_at = ? WHERE id = ?"
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            self.update_in(&mut tx, todo, true).await?;
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Update a todo whatever its stored version, discarding changes made
+    /// since `todo` was read
+    pub async fn overwrite_todo(&self, todo: &Todo) -> Result<()> {
+        self.overwrite_many(std::slice::from_ref(todo)).await
+    }
+
+    /// Move a todo to the trash
+    pub async fn delete_todo(&self, id: &str) -> Result<()> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            self.delete_in(&mut tx, id).await?;
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Update several todos in a single transaction; if any of them has
+    /// changed since it was read, none are updated and a [`Conflict`] is returned
+    pub async fn update_many(&self, todos: &[Todo]) -> Result<()> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            for todo in todos {
+                self.update_in(&mut tx, todo, true).await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Update several todos in a single transaction whatever their stored versions
+    pub async fn overwrite_many(&self, todos: &[Todo]) -> Result<()> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            for todo in todos {
+                self.update_in(&mut tx, todo, false).await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Move several todos to the trash in a single transaction
+    pub async fn delete_many(&self, ids: &[String]) -> Result<()> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            for id in ids {
+                self.delete_in(&mut tx, id).await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Write `todo` and record what changed in its history, provided it is
+    /// still at `todo.version` if `check_version` is set
+    ///
+    /// Fails if the todo does not exist or is in the trash. Moving an archived
+    /// todo to an open status takes it out of the archive.
+    async fn update_in(
+        &self,
+        conn: &mut SqliteConnection,
+        todo: &Todo,
+        check_version: bool,
+    ) -> Result<()> {
+        let before = audit::fetch(conn, &todo.id)
+            .await?
+            .filter(|before| before.deleted_at.is_none())
+            .ok_or_else(|| anyhow::anyhow!("todo not found: {}", todo.id))?;
+        let mut sql = "UPDATE todos SET title = ?, description = ?, status = ?, status_reason = ?, priority = ?, project = ?, tags = ?, due_date = ?, completed_at = ?, updated_at = ?, archived_at = CASE WHEN ? THEN archived_at END, version = version + 1 WHERE id = ? AND deleted_at IS NULL".to_string();
+        if check_version {
+            sql.push_str(" AND version = ?");
+        }
+        let mut query = sqlx::query(&sql)
+            .bind(&todo.title)
+            .bind(&todo.description)
+            .bind(todo.status)
+            .bind(&todo.status_reason)
+            .bind(todo.priority)
+            .bind(&todo.project)
+            .bind(Json(&todo.tags))
+            .bind(todo.due_date)
+            .bind(todo.completed_at.map(|t| t.to_rfc3339()))
+            .bind(todo.updated_at.to_rfc3339())
+            .bind(todo.status.is_closed())
+            .bind(&todo.id);
+        if check_version {
+            query = query.bind(todo.version);
+        }
+        if query.execute(&mut *conn).await?.rows_affected() == 0 {
+            // Only the version can have kept the row from matching
+            return Err(Conflict {
+                current: before,
+                expected: todo.version,
+            }
+            .into());
+        }
+        events::record_changes(conn, &before, todo).await?;
+        if before.archived_at.is_some() && !todo.status.is_closed() {
+            events::record_lifecycle(conn, &todo.id, EventKind::Unarchived, todo.updated_at)
+                .await?;
+        }
+        let after = audit::fetch(conn, &todo.id).await?;
+        audit::record(
+            conn,
+            &self.actor,
+            AuditAction::Update,
+            &todo.id,
+            Some(&before),
+            after.as_ref(),
         )
-        .bind(&todo.title)
-        .bind(&todo.description)
-        .bind(todo.completed)
-        .bind(todo.updated_at.to_rfc3339())
-        .bind(&todo.id)
-        .execute(&self.pool)
         .await?;
         Ok(())
     }
 
-    /// Delete a todo
-    pub async fn delete_todo(&self, id: &str) -> Result<()> {
-        sqlx::query("DELETE FROM todos WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
+    /// Soft-delete a todo by stamping `deleted_at`; see the `trash` module
+    async fn delete_in(&self, conn: &mut SqliteConnection, id: &str) -> Result<()> {
+        let now = Utc::now();
+        let before = audit::fetch(conn, id).await?;
+        let deleted =
+            sqlx::query("UPDATE todos SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+                .bind(now.to_rfc3339())
+                .bind(id)
+                .execute(&mut *conn)
+                .await?
+                .rows_affected();
+        if deleted > 0 {
+            events::record_lifecycle(conn, id, EventKind::Deleted, now).await?;
+            let after = audit::fetch(conn, id).await?;
+            audit::record(
+                conn,
+                &self.actor,
+                AuditAction::Delete,
+                id,
+                before.as_ref(),
+                after.as_ref(),
+            )
             .await?;
+        }
         Ok(())
     }
 
-    /// Get todos by completion status
-    pub async fn get_todos_by_status(&self, completed: bool) -> Result<Vec<Todo>> {
-        let todos = sqlx::query_as::<_, Todo>(
-            "SELECT * FROM todos WHERE completed = ? ORDER BY created_at DESC",
-        )
-        .bind(completed)
-        .fetch_all(&self.pool)
-        .await?;
-        Ok(todos)
+    /// Get todos with the given status
+    pub async fn get_todos_by_status(&self, status: Status) -> Result<Vec<Todo>> {
+        self.list_todos(&[status], &Sort::default()).await
     }
 }
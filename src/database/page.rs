@@ -0,0 +1,167 @@
+use super::{Param, Query, Sort, SortDirection, TodoDatabase};
+use crate::models::Todo;
+use crate::Result;
+use futures::stream::{self, Stream, TryStreamExt};
+use sqlx::query::QueryAs;
+use sqlx::sqlite::{Sqlite, SqliteArguments};
+
+/// Number of todos fetched at a time by [`TodoDatabase::stream_todos`]
+pub const PAGE_SIZE: u32 = 500;
+
+/// `WHERE` condition selecting the active todos matching `query`
+fn condition(query: Option<&Query>) -> (String, Vec<Param>) {
+    let mut sql = "deleted_at IS NULL AND archived_at IS NULL".to_string();
+    let mut params = Vec::new();
+    if let Some(query) = query {
+        let (condition, query_params) = query.to_sql();
+        sql.push_str(" AND ");
+        sql.push_str(&condition);
+        params = query_params;
+    }
+    (sql, params)
+}
+
+/// Bind compiled query parameters in order
+pub(super) fn bind<'q, O>(
+    mut query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    params: Vec<Param>,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    for param in params {
+        query = match param {
+            Param::Text(text) => query.bind(text),
+            Param::Status(status) => query.bind(status),
+            Param::Date(date) => query.bind(date),
+        };
+    }
+    query
+}
+
+impl TodoDatabase {
+    /// Count the todos matching `query` (all todos if `None`), leaving out
+    /// archived and trashed ones
+    pub async fn count_todos(&self, query: Option<&Query>) -> Result<u64> {
+        let (condition, params) = condition(query);
+        let sql = format!("SELECT COUNT(*) FROM todos WHERE {}", condition);
+        let (count,): (i64,) = bind(sqlx::query_as(&sql), params)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    /// Up to `limit` todos matching `query` in `sort` order, skipping the first `offset`
+    pub async fn list_window(
+        &self,
+        query: Option<&Query>,
+        sort: &Sort,
+        offset: u64,
+        limit: u32,
+    ) -> Result<Vec<Todo>> {
+        let (condition, params) = condition(query);
+        let sql = format!(
+            "SELECT * FROM todos WHERE {} {} LIMIT ? OFFSET ?",
+            condition,
+            sort.to_sql()
+        );
+        let todos = bind(sqlx::query_as::<_, Todo>(&sql), params)
+            .bind(limit)
+            .bind(offset as i64)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(todos)
+    }
+
+    /// Keyset page: up to `limit` todos matching `query` that come after the
+    /// todo with ID `after` in `sort` order, or the first page if `after` is `None`
+    ///
+    /// Unlike an offset, the cursor stays valid when todos before it are added
+    /// or removed. A page after an ID that no longer exists is empty.
+    pub async fn list_after(
+        &self,
+        query: Option<&Query>,
+        sort: &Sort,
+        after: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Todo>> {
+        let (condition, params) = condition(query);
+        let Some(after) = after else {
+            let sql = format!(
+                "SELECT * FROM todos WHERE {} {} LIMIT ?",
+                condition,
+                sort.to_sql()
+            );
+            let todos = bind(sqlx::query_as::<_, Todo>(&sql), params)
+                .bind(limit)
+                .fetch_all(&self.pool)
+                .await?;
+            return Ok(todos);
+        };
+
+        // Compare each row's sort values with those of the cursor row, from
+        // the last term (the ID tiebreak) outwards. Expressions are
+        // parenthesised since some, like `due_date IS NULL`, contain operators.
+        // `IS` is used for equality so NULL due dates compare equal; they are
+        // already separated from set dates by the preceding `due_date IS NULL` term.
+        let terms = sort.terms();
+        let keys: Vec<String> = terms
+            .iter()
+            .enumerate()
+            .map(|(i, (expression, _))| format!("{} AS k{}", expression, i))
+            .collect();
+        let mut keyset = "todos.id > last.last_id".to_string();
+        for (i, (expression, direction)) in terms.iter().enumerate().rev() {
+            let op = match direction {
+                SortDirection::Asc => ">",
+                SortDirection::Desc => "<",
+            };
+            keyset = format!(
+                "(({e}) {op} last.k{i} OR (({e}) IS last.k{i} AND {keyset}))",
+                e = expression,
+                op = op,
+                i = i,
+                keyset = keyset
+            );
+        }
+        let sql = format!(
+            "WITH last AS (SELECT {}, id AS last_id FROM todos WHERE id = ?) \
+             SELECT todos.* FROM todos, last WHERE {} AND {} {} LIMIT ?",
+            keys.join(", "),
+            condition,
+            keyset,
+            sort.to_sql()
+        );
+        let todos = bind(sqlx::query_as::<_, Todo>(&sql).bind(after), params)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(todos)
+    }
+
+    /// Stream every todo matching `query` in `sort` order, fetching
+    /// [`PAGE_SIZE`] todos at a time so memory use stays bounded
+    pub fn stream_todos(
+        &self,
+        query: Option<Query>,
+        sort: Sort,
+    ) -> impl Stream<Item = Result<Todo>> + '_ {
+        // State: the ID of the last todo seen, and whether the last page was full
+        stream::try_unfold((None::<String>, true), move |(after, more)| {
+            let query = query.clone();
+            let sort = sort.clone();
+            async move {
+                if !more {
+                    return Ok::<_, anyhow::Error>(None);
+                }
+                let page = self
+                    .list_after(query.as_ref(), &sort, after.as_deref(), PAGE_SIZE)
+                    .await?;
+                let more = page.len() == PAGE_SIZE as usize;
+                match page.last().map(|todo| todo.id.clone()) {
+                    Some(last) => Ok(Some((page, (Some(last), more)))),
+                    None => Ok(None),
+                }
+            }
+        })
+        .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+        .try_flatten()
+    }
+}
@@ -0,0 +1,678 @@
+//! Filter expressions such as
+//! `status:open and (due<2026-11-01 or title:report) and "quarterly"`
+//!
+//! A query is a boolean combination of terms. Terms next to each other are
+//! joined with `and`; `or` binds looser than `and`, and `not` (or a leading
+//! `-`) negates the term after it. Quoted or bare words search the title and
+//! description. Field terms are:
+//!
+//! - `status:<status>`, also `open` or `closed`, with `|` between alternatives
+//! - `title:<text>` and `description:<text>` (or `desc:`), substring matches
+//! - `due`, `created`, `updated` and `completed` compared with `:`, `=`, `!=`,
+//!   `<`, `<=`, `>` or `>=` to a date (`2026-11-01`, `today`, `+3d`, `friday`),
+//!   or to `none`/`any` with `:`
+//! - `id:<prefix>`
+//!
+//! Queries compile to SQL with every value bound as a parameter.
+
+use crate::models::{parse_due_date, Status};
+use chrono::{Local, NaiveDate};
+use std::fmt;
+use std::str::FromStr;
+
+/// Date column a query can compare against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Due,
+    Created,
+    Updated,
+    Completed,
+}
+
+impl DateField {
+    fn column(self) -> &'static str {
+        match self {
+            DateField::Due => "due_date",
+            DateField::Created => "created_at",
+            DateField::Updated => "updated_at",
+            DateField::Completed => "completed_at",
+        }
+    }
+
+    /// SQL expression for the calendar day of the column; timestamps are
+    /// stored in UTC, so their day is taken in local time like `today`
+    fn day(self) -> &'static str {
+        match self {
+            DateField::Due => "due_date",
+            DateField::Created => "date(created_at, 'localtime')",
+            DateField::Updated => "date(updated_at, 'localtime')",
+            DateField::Completed => "date(completed_at, 'localtime')",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DateField::Due => "due",
+            DateField::Created => "created",
+            DateField::Updated => "updated",
+            DateField::Completed => "completed",
+        }
+    }
+}
+
+/// Comparison operator of a field term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn sql(self) -> &'static str {
+        match self {
+            Comparison::Eq => "=",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Eq => ":",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+}
+
+/// Text column a query can search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    /// Title or description
+    Any,
+    Title,
+    Description,
+    /// Prefix of the todo ID
+    Id,
+}
+
+/// A single condition on a todo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    Status(Vec<Status>),
+    Text(TextField, String),
+    Date(DateField, Comparison, NaiveDate),
+    /// Whether the date is set (`due:any`) or not (`due:none`)
+    HasDate(DateField, bool),
+}
+
+/// Parsed filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Term(Term),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+/// Value bound to a placeholder of a compiled query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Param {
+    Text(String),
+    Status(Status),
+    Date(NaiveDate),
+}
+
+impl Query {
+    /// Todos with any of the given statuses
+    pub fn status(statuses: &[Status]) -> Self {
+        Query::Term(Term::Status(statuses.to_vec()))
+    }
+
+    /// Todos matching both this query and `other`
+    pub fn and(self, other: Query) -> Self {
+        Query::And(Box::new(self), Box::new(other))
+    }
+
+    /// Parse a query, resolving relative dates such as `today` against `today`
+    pub fn parse(input: &str, today: NaiveDate) -> crate::Result<Self> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            anyhow::bail!("empty filter");
+        }
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            today,
+        };
+        let query = parser.or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(query),
+            Some(Token::Close) => anyhow::bail!("unbalanced ')' in filter"),
+            Some(token) => anyhow::bail!("unexpected {} in filter", token),
+        }
+    }
+
+    /// SQL condition for this query and the values bound to its placeholders
+    pub fn to_sql(&self) -> (String, Vec<Param>) {
+        let mut params = Vec::new();
+        let sql = self.compile(&mut params);
+        (sql, params)
+    }
+
+    fn compile(&self, params: &mut Vec<Param>) -> String {
+        match self {
+            Query::Term(term) => term.compile(params),
+            Query::Not(inner) => format!("NOT {}", inner.compile(params)),
+            Query::And(left, right) => {
+                format!("({} AND {})", left.compile(params), right.compile(params))
+            }
+            Query::Or(left, right) => {
+                format!("({} OR {})", left.compile(params), right.compile(params))
+            }
+        }
+    }
+}
+
+impl Term {
+    /// SQL condition that is never NULL, so negation keeps todos without a value
+    fn compile(&self, params: &mut Vec<Param>) -> String {
+        match self {
+            Term::Status(statuses) if statuses.is_empty() => "0".to_string(),
+            Term::Status(statuses) => {
+                params.extend(statuses.iter().map(|s| Param::Status(*s)));
+                format!("status IN ({})", vec!["?"; statuses.len()].join(", "))
+            }
+            Term::Text(field, text) => {
+                let escaped = text
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                match field {
+                    TextField::Any => {
+                        let pattern = format!("%{}%", escaped);
+                        params.push(Param::Text(pattern.clone()));
+                        params.push(Param::Text(pattern));
+                        "(title LIKE ? ESCAPE '\\' OR COALESCE(description, '') LIKE ? ESCAPE '\\')"
+                            .to_string()
+                    }
+                    TextField::Title => {
+                        params.push(Param::Text(format!("%{}%", escaped)));
+                        "title LIKE ? ESCAPE '\\'".to_string()
+                    }
+                    TextField::Description => {
+                        params.push(Param::Text(format!("%{}%", escaped)));
+                        "COALESCE(description, '') LIKE ? ESCAPE '\\'".to_string()
+                    }
+                    TextField::Id => {
+                        params.push(Param::Text(format!("{}%", escaped)));
+                        "id LIKE ? ESCAPE '\\'".to_string()
+                    }
+                }
+            }
+            Term::Date(field, comparison, date) => {
+                params.push(Param::Date(*date));
+                format!(
+                    "({} IS NOT NULL AND {} {} ?)",
+                    field.column(),
+                    field.day(),
+                    comparison.sql()
+                )
+            }
+            Term::HasDate(field, true) => format!("{} IS NOT NULL", field.column()),
+            Term::HasDate(field, false) => format!("{} IS NULL", field.column()),
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Status(statuses) => {
+                let names: Vec<&str> = statuses.iter().map(|s| s.as_str()).collect();
+                write!(f, "status:{}", names.join("|"))
+            }
+            Term::Text(field, text) => {
+                let prefix = match field {
+                    TextField::Any => "",
+                    TextField::Title => "title:",
+                    TextField::Description => "description:",
+                    TextField::Id => "id:",
+                };
+                write!(f, "{}{}", prefix, quote(text))
+            }
+            Term::Date(field, comparison, date) => {
+                write!(f, "{}{}{}", field.name(), comparison.symbol(), date)
+            }
+            Term::HasDate(field, set) => {
+                write!(f, "{}:{}", field.name(), if *set { "any" } else { "none" })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::Term(term) => write!(f, "{}", term),
+            Query::Not(inner) => match **inner {
+                Query::Term(_) | Query::Not(_) => write!(f, "not {}", inner),
+                _ => write!(f, "not ({})", inner),
+            },
+            Query::And(left, right) => {
+                for (i, side) in [left, right].into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " and ")?;
+                    }
+                    match **side {
+                        Query::Or(..) => write!(f, "({})", side)?,
+                        _ => write!(f, "{}", side)?,
+                    }
+                }
+                Ok(())
+            }
+            Query::Or(left, right) => write!(f, "{} or {}", left, right),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Query::parse(s, Local::now().date_naive())
+    }
+}
+
+/// Quote `text` if it would not read back as a single word
+fn quote(text: &str) -> String {
+    let plain = !text.is_empty()
+        && !text.chars().any(|c| {
+            c.is_whitespace() || matches!(c, '(' | ')' | '"' | ':' | '<' | '>' | '=' | '!')
+        })
+        && !matches!(text.to_ascii_lowercase().as_str(), "and" | "or" | "not")
+        && !text.starts_with('-');
+    if plain {
+        text.to_string()
+    } else {
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    /// A leading `-`
+    Minus,
+    /// A bare word, including the keywords `and`, `or` and `not`
+    Word(String),
+    Quoted(String),
+    Field(String, Comparison, String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'"),
+            Token::Minus => write!(f, "'-'"),
+            Token::Word(word) => write!(f, "'{}'", word),
+            Token::Quoted(text) => write!(f, "\"{}\"", text),
+            Token::Field(name, comparison, value) => {
+                write!(f, "'{}{}{}'", name, comparison.symbol(), value)
+            }
+        }
+    }
+}
+
+fn tokenize(input: &str) -> crate::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Minus);
+            }
+            '"' => {
+                chars.next();
+                tokens.push(Token::Quoted(quoted(&mut chars)?));
+            }
+            _ => {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        name.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let comparison = match chars.peek() {
+                    Some(':') | Some('=') => Some(Comparison::Eq),
+                    Some('!') => Some(Comparison::Ne),
+                    Some('<') => Some(Comparison::Lt),
+                    Some('>') => Some(Comparison::Gt),
+                    _ => None,
+                };
+                match comparison {
+                    Some(comparison) if !name.is_empty() => {
+                        chars.next();
+                        let comparison = match (comparison, chars.peek()) {
+                            (Comparison::Ne, Some('=')) => Comparison::Ne,
+                            (Comparison::Ne, _) => anyhow::bail!("expected '!=' after {}", name),
+                            (Comparison::Lt, Some('=')) => Comparison::Le,
+                            (Comparison::Gt, Some('=')) => Comparison::Ge,
+                            (comparison, _) => {
+                                tokens.push(Token::Field(name, comparison, value(&mut chars)?));
+                                continue;
+                            }
+                        };
+                        chars.next();
+                        tokens.push(Token::Field(name, comparison, value(&mut chars)?));
+                    }
+                    _ => {
+                        let mut word = name;
+                        while let Some(&c) = chars.peek() {
+                            if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                                break;
+                            }
+                            word.push(c);
+                            chars.next();
+                        }
+                        tokens.push(Token::Word(word));
+                    }
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Read the value of a field term: a quoted string or everything up to the
+/// next space or parenthesis
+fn value(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> crate::Result<String> {
+    if chars.peek() == Some(&'"') {
+        chars.next();
+        return quoted(chars);
+    }
+    let mut value = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || matches!(c, '(' | ')') {
+            break;
+        }
+        value.push(c);
+        chars.next();
+    }
+    Ok(value)
+}
+
+/// Read the rest of a quoted string whose opening quote was consumed
+fn quoted(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> crate::Result<String> {
+    let mut text = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(text),
+            Some('\\') => match chars.next() {
+                Some(c) => text.push(c),
+                None => break,
+            },
+            Some(c) => text.push(c),
+            None => break,
+        }
+    }
+    anyhow::bail!("unterminated quote in filter")
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    today: NaiveDate,
+}
+
+impl Parser {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn or(&mut self) -> crate::Result<Query> {
+        let mut query = self.and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> crate::Result<Query> {
+        let mut query = self.unary()?;
+        loop {
+            if self.peek_keyword("and") {
+                self.pos += 1;
+            } else if self.peek_keyword("or")
+                || matches!(self.tokens.get(self.pos), None | Some(Token::Close))
+            {
+                return Ok(query);
+            }
+            query = Query::And(Box::new(query), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> crate::Result<Query> {
+        if self.peek_keyword("not") || self.tokens.get(self.pos) == Some(&Token::Minus) {
+            self.pos += 1;
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("filter ends unexpectedly"))?;
+        self.pos += 1;
+        match token {
+            Token::Open => {
+                let query = self.or()?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    anyhow::bail!("missing ')' in filter");
+                }
+                self.pos += 1;
+                Ok(query)
+            }
+            Token::Word(word) if ["and", "or"].contains(&word.to_ascii_lowercase().as_str()) => {
+                anyhow::bail!("unexpected '{}' in filter", word)
+            }
+            Token::Word(text) | Token::Quoted(text) => {
+                Ok(Query::Term(Term::Text(TextField::Any, text)))
+            }
+            Token::Field(name, comparison, value) => {
+                Ok(Query::Term(self.term(&name, comparison, &value)?))
+            }
+            Token::Close | Token::Minus => anyhow::bail!("unexpected {} in filter", token),
+        }
+    }
+
+    fn term(&self, name: &str, comparison: Comparison, value: &str) -> crate::Result<Term> {
+        let date_field = match name.to_ascii_lowercase().as_str() {
+            "status" => {
+                let statuses = self.statuses(value)?;
+                let statuses = match comparison {
+                    Comparison::Eq => statuses,
+                    Comparison::Ne => Status::ALL
+                        .into_iter()
+                        .filter(|s| !statuses.contains(s))
+                        .collect(),
+                    _ => anyhow::bail!("status only supports ':' and '!='"),
+                };
+                return Ok(Term::Status(statuses));
+            }
+            "title" | "description" | "desc" | "id" => {
+                if comparison != Comparison::Eq {
+                    anyhow::bail!("{} only supports ':'", name);
+                }
+                let field = match name.to_ascii_lowercase().as_str() {
+                    "title" => TextField::Title,
+                    "id" => TextField::Id,
+                    _ => TextField::Description,
+                };
+                return Ok(Term::Text(field, value.to_string()));
+            }
+            "due" | "due_date" => DateField::Due,
+            "created" | "created_at" => DateField::Created,
+            "updated" | "updated_at" => DateField::Updated,
+            "completed" | "completed_at" => DateField::Completed,
+            other => anyhow::bail!(
+                "unknown filter field: {} (expected status, title, description, id, due, created, updated or completed)",
+                other
+            ),
+        };
+        match (value.to_ascii_lowercase().as_str(), comparison) {
+            ("none", Comparison::Eq) | ("any", Comparison::Ne) => {
+                Ok(Term::HasDate(date_field, false))
+            }
+            ("any", Comparison::Eq) | ("none", Comparison::Ne) => {
+                Ok(Term::HasDate(date_field, true))
+            }
+            ("none" | "any", _) => anyhow::bail!("{} only compares with ':' or '!='", value),
+            _ => Ok(Term::Date(
+                date_field,
+                comparison,
+                parse_due_date(value, self.today)?,
+            )),
+        }
+    }
+
+    fn statuses(&self, value: &str) -> crate::Result<Vec<Status>> {
+        let mut statuses = Vec::new();
+        for name in value.split('|') {
+            match name.to_ascii_lowercase().as_str() {
+                "open" | "pending" => statuses.extend(Status::OPEN),
+                "closed" | "finished" => statuses.extend([Status::Done, Status::Cancelled]),
+                _ => statuses.push(name.parse()?),
+            }
+        }
+        let mut unique = Vec::new();
+        for status in statuses {
+            if !unique.contains(&status) {
+                unique.push(status);
+            }
+        }
+        Ok(unique)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_compile() {
+        let query = Query::parse(
+            "status:open and (due<2026-11-01 or title:report) \"50% done\"",
+            today(),
+        )
+        .unwrap();
+        assert_eq!(
+            query.to_string(),
+            "status:todo|in_progress|blocked|waiting and (due<2026-11-01 or title:report) and \"50% done\""
+        );
+        let (sql, params) = query.to_sql();
+        assert_eq!(
+            sql,
+            "((status IN (?, ?, ?, ?) AND ((due_date IS NOT NULL AND due_date < ?) OR title LIKE ? ESCAPE '\\')) \
+             AND (title LIKE ? ESCAPE '\\' OR COALESCE(description, '') LIKE ? ESCAPE '\\'))"
+        );
+        assert_eq!(params.len(), 8);
+        assert_eq!(
+            params[4],
+            Param::Date(NaiveDate::from_ymd_opt(2026, 11, 1).unwrap())
+        );
+        assert_eq!(params[6], Param::Text("%50\\% done%".to_string()));
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let query = Query::parse("-status:done due:none or completed>=today", today()).unwrap();
+        assert_eq!(
+            query.to_string(),
+            "not status:done and due:none or completed>=2026-10-14"
+        );
+        assert_eq!(
+            Query::parse("not (a or b)", today()).unwrap().to_string(),
+            "not (a or b)"
+        );
+        assert_eq!(
+            Query::parse("status!=done|cancelled", today()).unwrap(),
+            Query::status(&Status::OPEN)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for input in [
+            "",
+            "tag:work",
+            "priority>=high",
+            "(status:open",
+            "status:open)",
+            "\"open",
+            "status<done",
+            "due<someday",
+            "a and",
+            "or b",
+            "due<+1é",
+            "due>+99999999999d",
+        ] {
+            assert!(Query::parse(input, today()).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        let parsed = Query::parse(
+            "not (a or b) and status:done due>=2026-10-01 or -due:none \"x y\"",
+            today(),
+        )
+        .unwrap();
+        let built = [
+            Term::Text(TextField::Any, "ticket:42".to_string()),
+            Term::Text(TextField::Any, "a<b".to_string()),
+            Term::Text(TextField::Title, "x>=y".to_string()),
+            Term::Text(TextField::Description, "say \"hi\" \\ bye".to_string()),
+            Term::Text(TextField::Any, "wow!".to_string()),
+            Term::Text(TextField::Any, "or".to_string()),
+            Term::Text(TextField::Id, "a=b".to_string()),
+        ]
+        .into_iter()
+        .map(Query::Term);
+        for query in std::iter::once(parsed).chain(built) {
+            let text = query.to_string();
+            assert_eq!(Query::parse(&text, today()).unwrap(), query, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use super::TodoDatabase;
+use crate::Result;
+use std::future::Future;
+use std::time::Duration;
+
+/// Wait before the first retry; doubled for every further attempt
+const FIRST_BACKOFF: Duration = Duration::from_millis(20);
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Whether `error` means another connection holds a lock we need
+///
+/// The busy timeout already waits for most locks, but SQLite reports
+/// `SQLITE_BUSY` straight away when waiting could deadlock, e.g. when a
+/// transaction that has read tries to write while another one is writing.
+fn is_busy(error: &anyhow::Error) -> bool {
+    let Some(sqlx::Error::Database(error)) = error.downcast_ref::<sqlx::Error>() else {
+        return false;
+    };
+    // Extended result codes keep the primary code in the low byte
+    let code = error.code().and_then(|code| code.parse::<i32>().ok());
+    matches!(
+        code.map(|code| code & 0xff),
+        Some(SQLITE_BUSY | SQLITE_LOCKED)
+    )
+}
+
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+
+/// Delay before retry number `attempt` (from 0), with some jitter so
+/// competing processes don't retry in lockstep
+fn delay(attempt: u32) -> Duration {
+    let delay = FIRST_BACKOFF
+        .saturating_mul(1 << attempt.min(16))
+        .min(MAX_BACKOFF);
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos());
+    delay + delay.mul_f64(f64::from(nanos % 1000) / 2000.0)
+}
+
+impl TodoDatabase {
+    /// Run `op`, starting over with exponential backoff while the database is
+    /// busy, at most `busy_retries` times
+    ///
+    /// `op` must do all its work in one transaction, so a failed attempt
+    /// leaves nothing behind.
+    pub(super) async fn retry<T, F, Fut>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Err(e) if self.backoff(attempt, &e).await => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    /// Whether attempt number `attempt` (from 0), which failed with `error`,
+    /// should be started over; if so, waits before returning
+    pub(super) async fn backoff(&self, attempt: u32, error: &anyhow::Error) -> bool {
+        if attempt >= self.options.busy_retries || !is_busy(error) {
+            return false;
+        }
+        tokio::time::sleep(delay(attempt)).await;
+        true
+    }
+}
@@ -0,0 +1,243 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Field a todo list can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Created,
+    Updated,
+    Title,
+    /// Open todos before done or cancelled ones
+    Completion,
+    /// Workflow order: todo, in progress, blocked, waiting, done, cancelled
+    Status,
+    /// Due date, todos without one last
+    Due,
+    /// Manual order set with `move`
+    Position,
+}
+
+impl SortField {
+    /// Expressions ordered by; all but the last are always ascending
+    fn expressions(self) -> &'static [&'static str] {
+        match self {
+            SortField::Created => &["created_at"],
+            SortField::Updated => &["updated_at"],
+            SortField::Title => &["title COLLATE NOCASE"],
+            SortField::Completion => &["status IN ('done', 'cancelled')"],
+            SortField::Status => &[
+                "CASE status WHEN 'todo' THEN 0 WHEN 'in_progress' THEN 1 WHEN 'blocked' THEN 2 \
+                 WHEN 'waiting' THEN 3 WHEN 'done' THEN 4 ELSE 5 END",
+            ],
+            SortField::Due => &["due_date IS NULL", "due_date"],
+            SortField::Position => &["position"],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SortField::Created => "created",
+            SortField::Updated => "updated",
+            SortField::Title => "title",
+            SortField::Completion => "completion",
+            SortField::Status => "status",
+            SortField::Due => "due",
+            SortField::Position => "manual",
+        }
+    }
+}
+
+/// Sort direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// A single ordering term, e.g. `title:asc`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub direction: SortDirection,
+}
+
+impl SortKey {
+    pub fn asc(field: SortField) -> Self {
+        Self {
+            field,
+            direction: SortDirection::Asc,
+        }
+    }
+
+    pub fn desc(field: SortField) -> Self {
+        Self {
+            field,
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
+/// Multi-key sort specification, applied in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sort {
+    pub keys: Vec<SortKey>,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            keys: vec![SortKey::desc(SortField::Created)],
+        }
+    }
+}
+
+impl Sort {
+    /// Manual order, as arranged with `move`
+    pub fn manual() -> Self {
+        Self {
+            keys: vec![SortKey::asc(SortField::Position)],
+        }
+    }
+
+    /// Presets cycled through by the TUI sort key
+    pub fn presets() -> Vec<Sort> {
+        vec![
+            Sort::default(),
+            Sort {
+                keys: vec![SortKey::desc(SortField::Updated)],
+            },
+            Sort {
+                keys: vec![SortKey::asc(SortField::Title)],
+            },
+            Sort {
+                keys: vec![
+                    SortKey::asc(SortField::Completion),
+                    SortKey::desc(SortField::Created),
+                ],
+            },
+            Sort {
+                keys: vec![SortKey::asc(SortField::Due)],
+            },
+            Sort::manual(),
+        ]
+    }
+
+    /// SQL `ORDER BY` clause for this sort, built only from known column names
+    pub fn to_sql(&self) -> String {
+        let terms: Vec<String> = self
+            .effective_keys()
+            .iter()
+            .map(|key| {
+                let direction = match key.direction {
+                    SortDirection::Asc => "ASC",
+                    SortDirection::Desc => "DESC",
+                };
+                format!("{} {}", key.field.expressions().join(", "), direction)
+            })
+            .collect();
+        format!("ORDER BY {}, id ASC", terms.join(", "))
+    }
+
+    /// Every expression ordered by with its direction, not including the
+    /// final `id` tiebreak
+    pub(super) fn terms(&self) -> Vec<(&'static str, SortDirection)> {
+        let mut terms = Vec::new();
+        for key in self.effective_keys() {
+            let expressions = key.field.expressions();
+            let (last, leading) = expressions.split_last().expect("at least one expression");
+            terms.extend(leading.iter().map(|e| (*e, SortDirection::Asc)));
+            terms.push((*last, key.direction));
+        }
+        terms
+    }
+
+    fn effective_keys(&self) -> Vec<SortKey> {
+        if self.keys.is_empty() {
+            Sort::default().keys
+        } else {
+            self.keys.clone()
+        }
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| match key.direction {
+                SortDirection::Asc => format!("{}:asc", key.field.name()),
+                SortDirection::Desc => format!("{}:desc", key.field.name()),
+            })
+            .collect();
+        write!(f, "{}", terms.join(","))
+    }
+}
+
+impl FromStr for Sort {
+    type Err = anyhow::Error;
+
+    /// Parse a comma separated list of `field[:asc|desc]` terms
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys = Vec::new();
+        for term in s.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            let (name, direction) = match term.split_once(':') {
+                Some((name, direction)) => (name, Some(direction)),
+                None => (term, None),
+            };
+            let field = match name.to_ascii_lowercase().as_str() {
+                "created" | "created_at" => SortField::Created,
+                "updated" | "updated_at" => SortField::Updated,
+                "title" => SortField::Title,
+                "completion" | "completed" => SortField::Completion,
+                "status" => SortField::Status,
+                "due" | "due_date" => SortField::Due,
+                "manual" | "position" => SortField::Position,
+                other => anyhow::bail!("unknown sort field: {}", other),
+            };
+            let direction = match direction.map(|d| d.to_ascii_lowercase()).as_deref() {
+                None | Some("asc") => SortDirection::Asc,
+                Some("desc") => SortDirection::Desc,
+                Some(other) => anyhow::bail!("unknown sort direction: {}", other),
+            };
+            keys.push(SortKey { field, direction });
+        }
+        if keys.is_empty() {
+            anyhow::bail!("empty sort specification");
+        }
+        Ok(Self { keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multi_key() {
+        let sort: Sort = "completion, created:desc".parse().unwrap();
+        assert_eq!(
+            sort.keys,
+            vec![
+                SortKey::asc(SortField::Completion),
+                SortKey::desc(SortField::Created)
+            ]
+        );
+        assert_eq!(sort.to_string(), "completion:asc,created:desc");
+        assert_eq!(
+            sort.to_sql(),
+            "ORDER BY status IN ('done', 'cancelled') ASC, created_at DESC, id ASC"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown() {
+        assert!("priority".parse::<Sort>().is_err());
+        assert_eq!(
+            "due:desc".parse::<Sort>().unwrap().to_sql(),
+            "ORDER BY due_date IS NULL, due_date DESC, id ASC"
+        );
+        assert!("title:up".parse::<Sort>().is_err());
+        assert!("".parse::<Sort>().is_err());
+    }
+}
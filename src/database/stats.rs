@@ -0,0 +1,182 @@
+use super::TodoDatabase;
+use crate::models::{Status, Todo};
+use crate::Result;
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::HashMap;
+
+/// Number of oldest open todos included in [`Stats`]
+const OLDEST_OPEN_LIMIT: i64 = 5;
+
+/// Aggregate statistics over all todos
+#[derive(Debug, Clone)]
+pub struct Stats {
+    /// Number of todos per status, in workflow order
+    pub by_status: Vec<(Status, i64)>,
+    pub total: i64,
+    /// Todos created and completed on each of the last days, oldest first
+    pub per_day: Vec<Period>,
+    /// Todos created and completed in each of the last weeks (starting Monday), oldest first
+    pub per_week: Vec<Period>,
+    /// Mean time between creation and completion of completed todos
+    pub average_completion_time: Option<Duration>,
+    /// Open todos that were created the longest time ago
+    pub oldest_open: Vec<Todo>,
+}
+
+/// Created/completed counts for one day or week
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Period {
+    pub start: NaiveDate,
+    pub created: i64,
+    pub completed: i64,
+}
+
+impl Stats {
+    /// Share of todos that are done
+    pub fn completion_rate(&self) -> f64 {
+        let done = self
+            .by_status
+            .iter()
+            .find(|(status, _)| *status == Status::Done)
+            .map_or(0, |(_, count)| *count);
+        if self.total == 0 {
+            0.0
+        } else {
+            done as f64 / self.total as f64
+        }
+    }
+}
+
+impl TodoDatabase {
+    /// Compute statistics for the `days` days up to and including `today`
+    pub async fn stats(&self, days: u32, today: NaiveDate) -> Result<Stats> {
+        let days = days.max(1) as i64;
+
+        let counts: HashMap<Status, i64> = sqlx::query_as::<_, (Status, i64)>(
+            "SELECT status, COUNT(*) FROM todos WHERE deleted_at IS NULL GROUP BY status",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+        let by_status: Vec<(Status, i64)> = Status::ALL
+            .into_iter()
+            .map(|status| (status, counts.get(&status).copied().unwrap_or(0)))
+            .collect();
+        let total = by_status.iter().map(|(_, count)| count).sum();
+
+        let first_day = today - Duration::days(days - 1);
+        let per_day = self
+            .periods("date({ts}, 'localtime')", first_day, today, |day| day, days)
+            .await?;
+
+        let first_week = week_start(today) - Duration::weeks((days + 6) / 7 - 1);
+        let per_week = self
+            .periods(
+                "date({ts}, 'localtime', 'weekday 0', '-6 days')",
+                first_week,
+                today,
+                week_start,
+                (days + 6) / 7,
+            )
+            .await?;
+
+        let average_seconds: Option<f64> = sqlx::query_scalar(
+            "SELECT AVG((julianday(completed_at) - julianday(created_at)) * 86400.0) \
+             FROM todos WHERE completed_at IS NOT NULL AND deleted_at IS NULL",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let placeholders = vec!["?"; Status::OPEN.len()].join(", ");
+        let sql = format!(
+            "SELECT * FROM todos WHERE deleted_at IS NULL AND status IN ({}) \
+             ORDER BY created_at ASC LIMIT ?",
+            placeholders
+        );
+        let mut oldest = sqlx::query_as::<_, Todo>(&sql);
+        for status in Status::OPEN {
+            oldest = oldest.bind(status);
+        }
+        let oldest_open = oldest.bind(OLDEST_OPEN_LIMIT).fetch_all(&self.pool).await?;
+
+        Ok(Stats {
+            by_status,
+            total,
+            per_day,
+            per_week,
+            average_completion_time: average_seconds
+                .map(|secs| Duration::milliseconds((secs * 1000.0) as i64)),
+            oldest_open,
+        })
+    }
+
+    /// Created/completed counts grouped by `bucket`, an SQL expression over the
+    /// timestamp column `{ts}` that yields the period's start date. `key` maps a
+    /// date to its period start; `count` periods ending at `last` are returned.
+    ///
+    /// Timestamps are stored in UTC, so days are taken in local time to line up
+    /// with `first` and `last`.
+    async fn periods(
+        &self,
+        bucket: &str,
+        first: NaiveDate,
+        last: NaiveDate,
+        key: fn(NaiveDate) -> NaiveDate,
+        count: i64,
+    ) -> Result<Vec<Period>> {
+        let query = |column: &str| {
+            let bucket = bucket.replace("{ts}", column);
+            format!(
+                "SELECT {bucket} AS period, COUNT(*) FROM todos \
+                 WHERE deleted_at IS NULL AND {column} IS NOT NULL AND date({column}, 'localtime') BETWEEN ? AND ? GROUP BY period"
+            )
+        };
+        let created: HashMap<NaiveDate, i64> =
+            sqlx::query_as::<_, (NaiveDate, i64)>(&query("created_at"))
+                .bind(first)
+                .bind(last)
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .collect();
+        let completed: HashMap<NaiveDate, i64> =
+            sqlx::query_as::<_, (NaiveDate, i64)>(&query("completed_at"))
+                .bind(first)
+                .bind(last)
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .collect();
+
+        let mut periods = Vec::new();
+        let mut start = key(last);
+        for _ in 0..count {
+            periods.push(Period {
+                start,
+                created: created.get(&start).copied().unwrap_or(0),
+                completed: completed.get(&start).copied().unwrap_or(0),
+            });
+            start = key(start - Duration::days(1));
+        }
+        periods.reverse();
+        Ok(periods)
+    }
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Compact human readable duration, e.g. `3d 4h`, `5h 12m` or `7m`
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
@@ -0,0 +1,82 @@
+use super::{audit, TodoDatabase};
+use crate::models::Todo;
+use crate::Result;
+use futures::future::BoxFuture;
+use sqlx::Sqlite;
+
+/// Changes made through [`TodoDatabase::transaction`], committed together
+///
+/// Each change records its history and audit entries as the single-row
+/// methods of [`TodoDatabase`] do, within the same transaction.
+pub struct Transaction<'a> {
+    db: &'a TodoDatabase,
+    tx: sqlx::Transaction<'static, Sqlite>,
+}
+
+impl Transaction<'_> {
+    /// Get a todo by ID, unless it is in the trash, seeing changes made so far
+    pub async fn get_todo(&mut self, id: &str) -> Result<Option<Todo>> {
+        Ok(audit::fetch(&mut self.tx, id)
+            .await?
+            .filter(|todo| todo.deleted_at.is_none()))
+    }
+
+    pub async fn create_todo(&mut self, todo: &Todo) -> Result<()> {
+        self.db.create_in(&mut self.tx, todo).await
+    }
+
+    /// Update a todo, provided it is still at `todo.version`; see
+    /// [`TodoDatabase::update_todo`]
+    pub async fn update_todo(&mut self, todo: &Todo) -> Result<()> {
+        self.db.update_in(&mut self.tx, todo, true).await
+    }
+
+    /// Update a todo whatever its stored version
+    pub async fn overwrite_todo(&mut self, todo: &Todo) -> Result<()> {
+        self.db.update_in(&mut self.tx, todo, false).await
+    }
+
+    /// Move a todo to the trash
+    pub async fn delete_todo(&mut self, id: &str) -> Result<()> {
+        self.db.delete_in(&mut self.tx, id).await
+    }
+
+    /// Take a todo back out of the trash, returning whether it was there
+    pub async fn restore_todo(&mut self, id: &str) -> Result<bool> {
+        self.db.restore_in(&mut self.tx, id).await
+    }
+}
+
+impl TodoDatabase {
+    /// Run `f` in a transaction, committing if it succeeds and rolling every
+    /// change back if it fails
+    ///
+    /// `f` is called again if the database was busy, so it should not have
+    /// effects outside the transaction.
+    pub async fn transaction<'a, T, F>(&'a self, mut f: F) -> Result<T>
+    where
+        F: for<'t> FnMut(&'t mut Transaction<'a>) -> BoxFuture<'t, Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match self.transaction_once(&mut f).await {
+                Err(e) if self.backoff(attempt, &e).await => attempt += 1,
+                result => return result,
+            }
+        }
+    }
+
+    async fn transaction_once<'a, T, F>(&'a self, f: &mut F) -> Result<T>
+    where
+        F: for<'t> FnMut(&'t mut Transaction<'a>) -> BoxFuture<'t, Result<T>>,
+    {
+        let mut tx = Transaction {
+            db: self,
+            tx: self.pool.begin().await?,
+        };
+        // Dropping the transaction without committing rolls it back
+        let value = f(&mut tx).await?;
+        tx.tx.commit().await?;
+        Ok(value)
+    }
+}
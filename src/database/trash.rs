@@ -0,0 +1,110 @@
+use super::{audit, events, TodoDatabase};
+use crate::models::{AuditAction, EventKind, Todo};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqliteConnection;
+
+impl TodoDatabase {
+    /// Todos in the trash, most recently deleted first
+    pub async fn list_trash(&self) -> Result<Vec<Todo>> {
+        let todos = sqlx::query_as::<_, Todo>(
+            "SELECT * FROM todos WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(todos)
+    }
+
+    /// Take a todo back out of the trash, returning whether it was there
+    pub async fn restore_todo(&self, id: &str) -> Result<bool> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            let restored = self.restore_in(&mut tx, id).await?;
+            tx.commit().await?;
+            Ok(restored)
+        })
+        .await
+    }
+
+    pub(super) async fn restore_in(&self, conn: &mut SqliteConnection, id: &str) -> Result<bool> {
+        let before = audit::fetch(conn, id).await?;
+        let restored = sqlx::query(
+            "UPDATE todos SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected()
+            > 0;
+        if restored {
+            events::record_lifecycle(conn, id, EventKind::Restored, Utc::now()).await?;
+            let after = audit::fetch(conn, id).await?;
+            audit::record(
+                conn,
+                &self.actor,
+                AuditAction::Restore,
+                id,
+                before.as_ref(),
+                after.as_ref(),
+            )
+            .await?;
+        }
+        Ok(restored)
+    }
+
+    /// Permanently remove todos from the trash, returning how many were removed
+    pub async fn purge_many(&self, ids: &[String]) -> Result<u64> {
+        self.retry(|| async move {
+            let mut tx = self.pool.begin().await?;
+            let now = Utc::now();
+            let mut purged = 0;
+            for id in ids {
+                let before = audit::fetch(&mut tx, id).await?;
+                let removed =
+                    sqlx::query("DELETE FROM todos WHERE id = ? AND deleted_at IS NOT NULL")
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?
+                        .rows_affected();
+                if removed > 0 {
+                    events::record_lifecycle(&mut tx, id, EventKind::Purged, now).await?;
+                    audit::record(
+                        &mut tx,
+                        &self.actor,
+                        AuditAction::Purge,
+                        id,
+                        before.as_ref(),
+                        None,
+                    )
+                    .await?;
+                    purged += removed;
+                }
+            }
+            tx.commit().await?;
+            Ok(purged)
+        })
+        .await
+    }
+
+    /// Permanently remove everything in the trash, or only what was deleted
+    /// before `deleted_before`, returning how many todos were removed
+    pub async fn empty_trash(&self, deleted_before: Option<DateTime<Utc>>) -> Result<u64> {
+        let ids: Vec<String> = match deleted_before {
+            Some(cutoff) => {
+                sqlx::query_scalar(
+                    "SELECT id FROM todos WHERE deleted_at IS NOT NULL \
+                     AND julianday(deleted_at) < julianday(?)",
+                )
+                .bind(cutoff.to_rfc3339())
+                .fetch_all(&self.pool)
+                .await?
+            }
+            None => {
+                sqlx::query_scalar("SELECT id FROM todos WHERE deleted_at IS NOT NULL")
+                    .fetch_all(&self.pool)
+                    .await?
+            }
+        };
+        self.purge_many(&ids).await
+    }
+}
@@ -0,0 +1,64 @@
+use super::{Query, TodoDatabase};
+use crate::models::SavedView;
+use crate::Result;
+use chrono::Utc;
+
+impl TodoDatabase {
+    /// Saved views in name order
+    pub async fn list_views(&self) -> Result<Vec<SavedView>> {
+        let views = sqlx::query_as::<_, SavedView>("SELECT * FROM views ORDER BY name")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(views)
+    }
+
+    /// Get a saved view by name
+    pub async fn get_view(&self, name: &str) -> Result<Option<SavedView>> {
+        let view = sqlx::query_as::<_, SavedView>("SELECT * FROM views WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(view)
+    }
+
+    /// Save `query` under `name`, replacing any view with that name
+    ///
+    /// The query is checked before saving but stored as written.
+    pub async fn save_view(&self, name: &str, query: &str) -> Result<SavedView> {
+        SavedView::validate_name(name)?;
+        query.parse::<Query>()?;
+        let now = Utc::now().to_rfc3339();
+        let now = now.as_str();
+        self.retry(|| async move {
+            sqlx::query(
+                "INSERT INTO views (name, query, created_at, updated_at) VALUES (?, ?, ?, ?) \
+                 ON CONFLICT (name) DO UPDATE SET query = excluded.query, updated_at = excluded.updated_at",
+            )
+            .bind(name)
+            .bind(query.trim())
+            .bind(now)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await?;
+        self.get_view(name)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("view {} was not saved", name))
+    }
+
+    /// Delete a saved view, returning whether it existed
+    pub async fn delete_view(&self, name: &str) -> Result<bool> {
+        self.retry(|| async move {
+            let deleted = sqlx::query("DELETE FROM views WHERE name = ?")
+                .bind(name)
+                .execute(&self.pool)
+                .await?
+                .rows_affected()
+                > 0;
+            Ok(deleted)
+        })
+        .await
+    }
+}
@@ -0,0 +1,38 @@
+use super::TodoDatabase;
+use crate::Result;
+use futures::stream::{self, Stream};
+use sqlx::SqliteConnection;
+use std::time::Duration;
+
+impl TodoDatabase {
+    /// Stream that yields whenever another connection, e.g. another `todo`
+    /// process, commits a change, checking every `every`
+    ///
+    /// Uses `PRAGMA data_version` on a connection of its own, so writes made
+    /// through this handle's pool are reported too.
+    pub async fn changes(&self, every: Duration) -> Result<impl Stream<Item = Result<()>>> {
+        let mut conn = self.pool.acquire().await?.detach();
+        let version = data_version(&mut conn).await?;
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Ok(stream::try_unfold(
+            (conn, version, interval),
+            |(mut conn, version, mut interval)| async move {
+                loop {
+                    interval.tick().await;
+                    let current = data_version(&mut conn).await?;
+                    if current != version {
+                        return Ok(Some(((), (conn, current, interval))));
+                    }
+                }
+            },
+        ))
+    }
+}
+
+async fn data_version(conn: &mut SqliteConnection) -> Result<i64> {
+    let version = sqlx::query_scalar("PRAGMA data_version")
+        .fetch_one(conn)
+        .await?;
+    Ok(version)
+}
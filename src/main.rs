@@ -1,5 +1,13 @@
-use clap::{Parser, Subcommand};
-use template_rust::{database::TodoDatabase, models::Todo, tui::App};
+use chrono::Local;
+use clap::{Args, Parser, Subcommand};
+use futures::TryStreamExt;
+use std::path::{Path, PathBuf};
+use template_rust::{
+    config::{Config, ConfigFile, OutputFormat, KEYS, PROJECT_FILE},
+    database::{format_duration, Key, Placement, Query, Sort, TodoDatabase},
+    models::{parse_age, parse_due_date, Priority, Status, Todo, TransitionRules},
+    tui::{App, View},
+};
 
 /// A simple todo application with SQLite and TUI
 #[derive(Parser)]
@@ -9,23 +17,85 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Database file path
-    #[arg(short, long, default_value = "todo.db")]
-    database: String,
+    /// Database file path (default from config or a .todo marker, else
+    /// $XDG_DATA_HOME/todo/todo.db)
+    #[arg(short, long)]
+    database: Option<String>,
+
+    /// Output format of listings: text or json (default from config, else text)
+    #[arg(long, global = true)]
+    format: Option<OutputFormat>,
+
+    /// Allowed status transitions, e.g. "todo:in_progress|cancelled;in_progress:done"
+    /// (default from config, else all transitions are allowed)
+    #[arg(long, global = true)]
+    transitions: Option<TransitionRules>,
+
+    /// Archive done and cancelled todos finished longer ago than this, e.g. 30d
+    #[arg(long, global = true, value_parser = parse_age)]
+    auto_archive: Option<chrono::Duration>,
+
+    /// Name recorded in the audit log for changes (defaults to the OS user)
+    #[arg(long, global = true)]
+    identity: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Start the interactive TUI
-    Tui,
+    Tui {
+        /// Ask for confirmation before deleting todos
+        #[arg(long)]
+        confirm: bool,
+        /// Initial sort order, e.g. "title" or "completion,created:desc"
+        #[arg(short, long)]
+        sort: Option<Sort>,
+        /// Start in the kanban board view
+        #[arg(short, long)]
+        board: bool,
+        /// Board column WIP limit as STATUS=N, e.g. in_progress=3 (repeatable;
+        /// overrides the configured limit for that status)
+        #[arg(long = "wip-limit", value_parser = parse_wip_limit)]
+        wip_limits: Vec<(Status, usize)>,
+        /// Initial filter query, as typed into the filter bar with '/'
+        #[arg(short, long = "where", value_name = "QUERY")]
+        r#where: Option<Query>,
+    },
     /// List all todos
     List {
+        /// Saved view to list, as @name (see `view save`)
+        #[arg(value_name = "@VIEW", value_parser = parse_view_ref, conflicts_with = "archived")]
+        view: Option<String>,
         /// Show only completed todos
         #[arg(short, long)]
         completed: bool,
-        /// Show only pending todos
+        /// Show only pending (not done or cancelled) todos
         #[arg(short, long)]
         pending: bool,
+        /// Show only todos with this status
+        #[arg(long, conflicts_with_all = ["completed", "pending"])]
+        status: Option<Status>,
+        /// Show archived todos instead
+        #[arg(short, long, conflicts_with_all = ["completed", "pending", "status"])]
+        archived: bool,
+        /// Sort order, e.g. "completion,created:desc" (fields: created, updated, title, completion, status, due, manual)
+        #[arg(short, long)]
+        sort: Option<Sort>,
+        /// Filter query, e.g. 'status:open and (due<+7d or title:report) and "draft"'
+        /// (fields: status, title, description, id, due, created, updated, completed)
+        #[arg(
+            short,
+            long = "where",
+            value_name = "QUERY",
+            conflicts_with = "archived"
+        )]
+        r#where: Option<Query>,
+        /// Show at most this many todos
+        #[arg(long)]
+        limit: Option<u32>,
+        /// Skip this many todos first
+        #[arg(long, default_value_t = 0)]
+        offset: u64,
     },
     /// Add a new todo
     Add {
@@ -34,75 +104,861 @@ enum Commands {
         /// Optional description
         #[arg(short, long)]
         description: Option<String>,
+        /// Due date: YYYY-MM-DD, today, tomorrow, +3d, +1w or a weekday name
+        #[arg(long)]
+        due: Option<String>,
+        /// Priority: low, medium or high
+        #[arg(long)]
+        priority: Option<Priority>,
+        /// Project the todo belongs to
+        #[arg(long)]
+        project: Option<String>,
+        /// Tag to add; may be repeated
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+    /// Set or clear the due date of a todo
+    Due {
+        /// Todo ID
+        id: String,
+        /// Due date (YYYY-MM-DD, today, tomorrow, +3d, +1w, monday, ...) or "none"
+        date: String,
     },
     /// Complete a todo by ID
     Complete {
         /// Todo ID
         id: String,
     },
-    /// Delete a todo by ID
+    /// Start working on a todo (mark it in progress)
+    Start {
+        /// Todo ID
+        id: String,
+    },
+    /// Mark a todo as blocked
+    Block {
+        /// Todo ID
+        id: String,
+        /// What the todo is blocked on
+        #[arg(short, long)]
+        reason: Option<String>,
+    },
+    /// Set the status of a todo
+    #[command(name = "status")]
+    SetStatus {
+        /// Todo ID
+        id: String,
+        /// New status: todo, in_progress, blocked, waiting, done or cancelled
+        status: Status,
+        /// Why the todo has this status, e.g. what it is waiting on
+        #[arg(short, long)]
+        reason: Option<String>,
+    },
+    /// Move a todo to the trash by ID
     Delete {
         /// Todo ID
         id: String,
     },
+    /// Move a todo in manual order (see `list --sort manual`)
+    Move {
+        /// Todo ID
+        id: String,
+        /// Place the todo directly before this todo
+        #[arg(long, conflicts_with = "after", required_unless_present = "after")]
+        before: Option<String>,
+        /// Place the todo directly after this todo
+        #[arg(long)]
+        after: Option<String>,
+    },
+    /// Archive a finished todo, or all done and cancelled todos
+    Archive {
+        /// Todo ID; archives every done or cancelled todo when omitted
+        id: Option<String>,
+        /// Only archive todos completed before this date (YYYY-MM-DD, today, ...)
+        #[arg(long, conflicts_with = "id")]
+        completed_before: Option<String>,
+    },
+    /// Take a todo back out of the archive
+    Unarchive {
+        /// Todo ID
+        id: String,
+    },
+    /// Save, list or delete named filter queries (saved views)
+    View {
+        #[command(subcommand)]
+        command: ViewCommand,
+    },
+    /// List, restore or permanently delete trashed todos
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+    /// Show every recorded change to a todo, oldest first
+    History {
+        /// Todo ID (deleted todos keep their history)
+        id: String,
+    },
+    /// Show the audit log of changes to all todos, oldest first
+    Log {
+        /// Only changes since this date (YYYY-MM-DD, today, ...) or age (e.g. 7d)
+        #[arg(long, value_parser = parse_since)]
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        /// Only changes made by this actor
+        #[arg(long)]
+        actor: Option<String>,
+    },
+    /// Read or change configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Show statistics: counts by status, completion rate and timing
+    Stats {
+        /// Number of days covered by the daily and weekly breakdown
+        #[arg(long, default_value_t = 14)]
+        days: u32,
+    },
+    /// Print which database is in use and which setting chose it
+    Where,
+    /// Back up, restore, check, encrypt or decrypt the database file
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Write a consistent copy of the database, even while it is in use
+    Backup {
+        /// File to write; must not exist yet
+        path: PathBuf,
+    },
+    /// Replace the database with a backup; close other todo processes first
+    Restore {
+        /// Backup to restore
+        path: PathBuf,
+    },
+    /// Check the database file and the stored values for corruption
+    Check,
+    /// Encrypt the database with a new key, or the configured one; close
+    /// other todo processes first
+    Encrypt(NewKey),
+    /// Store the database, opened with the configured key, unencrypted; close
+    /// other todo processes first
+    Decrypt,
+    /// Change the key of the database from the configured one to a new key;
+    /// close other todo processes first
+    Rekey(NewKey),
+}
 
-    let db = TodoDatabase::new(&cli.database).await?;
+/// Key to encrypt the database with: a key file, or a passphrase taken from
+/// `TODO_NEW_PASSPHRASE` so it stays out of the shell history
+#[derive(Args)]
+struct NewKey {
+    /// File whose contents are the new key
+    #[arg(long)]
+    key_file: Option<PathBuf>,
+}
 
-    match cli.command {
-        Some(Commands::Tui) | None => {
-            // Default to TUI mode
-            let mut app = App::new(db);
-            app.run().await?;
-        }
-        Some(Commands::List { completed, pending }) => {
-            let todos = if completed {
-                db.get_todos_by_status(true).await?
-            } else if pending {
-                db.get_todos_by_status(false).await?
-            } else {
-                db.get_all_todos().await?
-            };
+impl NewKey {
+    fn resolve(&self) -> Option<Key> {
+        self.key_file.clone().map(Key::File).or_else(|| {
+            std::env::var("TODO_NEW_PASSPHRASE")
+                .ok()
+                .filter(|passphrase| !passphrase.is_empty())
+                .map(Key::Passphrase)
+        })
+    }
+}
 
-            if todos.is_empty() {
-                println!("No todos found.");
-            } else {
-                for todo in todos {
-                    let status = if todo.completed { "✓" } else { "○" };
-                    println!("{} {} - {}", status, todo.title, todo.id);
-                    if let Some(description) = &todo.description {
-                        println!("   {}", description);
-                    }
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective value of a setting, or of all settings
+    Get {
+        /// database, filter, sort, theme, keymap (or keymap.<key>), output, identity,
+        /// auto_archive, wal, busy_timeout, busy_retries or key_file
+        key: Option<String>,
+    },
+    /// Change a setting in the user config file
+    Set {
+        /// Setting to change; key bindings are set as keymap.<key>
+        key: String,
+        value: String,
+        /// Write to the project config file instead (the nearest .todo.toml, or
+        /// a new one in the current directory)
+        #[arg(long)]
+        local: bool,
+    },
+    /// Print the config file locations, in order of increasing precedence
+    Path,
+}
+
+#[derive(Subcommand)]
+enum ViewCommand {
+    /// Save a filter query under a name, replacing any view with that name
+    Save {
+        /// View name, used as @name
+        name: String,
+        /// Filter query, as for `list --where`
+        query: String,
+    },
+    /// List saved views
+    List,
+    /// Delete a saved view
+    Delete {
+        /// View name
+        #[arg(value_parser = parse_view_name)]
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashCommand {
+    /// List todos in the trash, most recently deleted first
+    List,
+    /// Take a todo back out of the trash
+    Restore {
+        /// Todo ID
+        id: String,
+    },
+    /// Permanently delete todos in the trash
+    Empty {
+        /// Only delete todos trashed longer ago than this, e.g. 30d, 2w or 12h
+        #[arg(long, value_parser = parse_age)]
+        older_than: Option<chrono::Duration>,
+    },
+}
+
+/// Parse `@name`, the way saved views are referred to on the command line
+fn parse_view_ref(s: &str) -> Result<String, String> {
+    s.strip_prefix('@')
+        .map(str::to_string)
+        .ok_or_else(|| format!("expected a saved view as @name, got '{}'", s))
+}
+
+/// Parse a view name, with or without its leading `@`
+fn parse_view_name(s: &str) -> Result<String, String> {
+    Ok(s.strip_prefix('@').unwrap_or(s).to_string())
+}
+
+fn parse_wip_limit(s: &str) -> Result<(Status, usize), String> {
+    let (status, limit) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected STATUS=N, got '{}'", s))?;
+    let status = status.parse::<Status>().map_err(|e| e.to_string())?;
+    let limit = limit.parse::<usize>().map_err(|e| e.to_string())?;
+    Ok((status, limit))
+}
+
+/// Render counts as a one-line bar chart, scaled to the largest value
+fn sparkline(values: &[i64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|&v| BARS[(v.max(0) * (BARS.len() as i64 - 1) / max) as usize])
+        .collect()
+}
+
+/// Parse a point in time given as an age (`7d`) or a local date (`2026-10-01`, `today`)
+fn parse_since(s: &str) -> anyhow::Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(age) = parse_age(s) {
+        return age_cutoff(age);
+    }
+    let date = parse_due_date(s, Local::now().date_naive())?;
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map(|start| start.with_timezone(&chrono::Utc))
+        .ok_or_else(|| anyhow::anyhow!("invalid local date: {}", date))
+}
+
+/// The point in time `age` ago
+fn age_cutoff(age: chrono::Duration) -> anyhow::Result<chrono::DateTime<chrono::Utc>> {
+    chrono::Utc::now()
+        .checked_sub_signed(age)
+        .ok_or_else(|| anyhow::anyhow!("age out of range: {} days", age.num_days()))
+}
+
+async fn set_status(
+    db: &TodoDatabase,
+    id: &str,
+    status: Status,
+    reason: Option<String>,
+    rules: &TransitionRules,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(mut todo) = db.get_todo(id).await? {
+        todo.transition(status, reason, rules)?;
+        db.update_todo(&todo).await?;
+        let verb = match status {
+            Status::Todo => "reopened",
+            Status::InProgress => "started",
+            Status::Blocked => "blocked",
+            Status::Waiting => "waiting",
+            Status::Done => "completed",
+            Status::Cancelled => "cancelled",
+        };
+        println!("Todo {}: {}", verb, todo.title);
+    } else {
+        eprintln!("Todo not found: {}", id);
+    }
+    Ok(())
+}
+
+/// Print todos as a list or a JSON array, consuming the stream as it goes
+async fn print_todos(
+    todos: impl futures::Stream<Item = template_rust::Result<Todo>>,
+    output: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    futures::pin_mut!(todos);
+    let mut count = 0;
+    while let Some(todo) = todos.try_next().await? {
+        if output == OutputFormat::Json {
+            let json = serde_json::to_string_pretty(&todo)?.replace('\n', "\n  ");
+            print!("{}\n  {}", if count == 0 { "[" } else { "," }, json);
+        } else {
+            print!("{} {} - {}", todo.status.symbol(), todo.title, todo.id);
+            if let Some(priority) = todo.priority {
+                print!(" {}", priority.symbol());
+            }
+            if let Some(due) = todo.due_date {
+                print!(" (due {})", due);
+            }
+            if let Some(project) = &todo.project {
+                print!(" in {}", project);
+            }
+            for tag in &todo.tags {
+                print!(" #{}", tag);
+            }
+            match &todo.status_reason {
+                Some(reason) => println!(" [{}: {}]", todo.status.label(), reason),
+                None if !matches!(todo.status, Status::Todo | Status::Done) => {
+                    println!(" [{}]", todo.status.label())
                 }
+                None => println!(),
+            }
+            if let Some(description) = &todo.description {
+                println!("   {}", description);
             }
         }
-        Some(Commands::Add { title, description }) => {
-            let todo = Todo::new(title, description);
-            db.create_todo(&todo).await?;
-            println!("Todo added: {}", todo.id);
+        count += 1;
+    }
+    match output {
+        OutputFormat::Json if count == 0 => println!("[]"),
+        OutputFormat::Json => println!("\n]"),
+        OutputFormat::Text if count == 0 => println!("No todos found."),
+        OutputFormat::Text => {}
+    }
+    Ok(())
+}
+
+/// Print `value` as pretty JSON
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Handle `config get|set|path`, which must work even when the config is invalid
+fn config_command(command: ConfigCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let cwd = std::env::current_dir()?;
+    match command {
+        ConfigCommand::Get { key: Some(key) } => {
+            if let Some(value) = Config::sources()?.get(&key)? {
+                println!("{}", value);
+            }
         }
-        Some(Commands::Complete { id }) => {
-            if let Some(mut todo) = db.get_todo(&id).await? {
-                todo.complete();
-                db.update_todo(&todo).await?;
-                println!("Todo completed: {}", todo.title);
-            } else {
-                eprintln!("Todo not found: {}", id);
+        ConfigCommand::Get { key: None } => {
+            let sources = Config::sources()?;
+            for key in KEYS {
+                if let Some(value) = sources.get(key)? {
+                    println!("{} = {}", key, value);
+                }
             }
         }
-        Some(Commands::Delete { id }) => {
-            if let Some(todo) = db.get_todo(&id).await? {
-                db.delete_todo(&id).await?;
-                println!("Todo deleted: {}", todo.title);
+        ConfigCommand::Set { key, value, local } => {
+            let path = if local {
+                Config::project_path(&cwd).unwrap_or_else(|| cwd.join(PROJECT_FILE))
             } else {
-                eprintln!("Todo not found: {}", id);
+                Config::user_path().ok_or("cannot locate the user config directory")?
+            };
+            let mut file = ConfigFile::read(&path)?.unwrap_or_default();
+            file.set(&key, &value)?;
+            file.write(&path)?;
+            println!("Set {} = {} in {}", key, value, path.display());
+        }
+        ConfigCommand::Path => {
+            let project = Config::project_path(&cwd);
+            let paths: [(&str, Option<PathBuf>); 2] =
+                [("user", Config::user_path()), ("project", project)];
+            for (name, path) in paths {
+                match path {
+                    Some(path) if path.exists() => println!("{}: {}", name, path.display()),
+                    Some(path) => println!("{}: {} (not found)", name, path.display()),
+                    None => println!("{}: none", name),
+                }
             }
+            println!("env: TODO_DATABASE, TODO_FILTER, TODO_SORT, TODO_THEME, TODO_KEYMAP, TODO_OUTPUT, TODO_IDENTITY, TODO_AUTO_ARCHIVE, TODO_WAL, TODO_BUSY_TIMEOUT, TODO_BUSY_RETRIES, TODO_KEY_FILE, TODO_PASSPHRASE, TODO_TRANSITIONS, TODO_WIP_LIMITS");
         }
     }
+    Ok(())
+}
 
+fn where_command(database: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let (source, path) = match database {
+        Some(path) => ("command line".to_string(), path),
+        None => Config::layers()?
+            .into_iter()
+            .rev()
+            .find_map(|(source, layer)| layer.database.map(|path| (source, path)))
+            .ok_or("no database configured")?,
+    };
+    let path = match path.as_str() {
+        ":memory:" => path,
+        url if url.starts_with("sqlite:") => path,
+        file => std::path::absolute(file)?.display().to_string(),
+    };
+    println!("{}", path);
+    println!("  from {}", source);
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    if let Some(Commands::Config { command }) = cli.command {
+        return config_command(command);
+    }
+    if let Some(Commands::Where) = cli.command {
+        return where_command(cli.database);
+    }
+
+    // Command line flags take precedence over the config files and environment
+    let config = Config::load()?;
+    let database = cli.database.unwrap_or(config.database_url);
+    let output = cli.format.unwrap_or(config.output);
+
+    // These replace the file, so it must not be opened (and migrated) first
+    if let Some(Commands::Db { command }) = &cli.command {
+        let path = Path::new(&database);
+        let key = config.database_options.key.as_ref();
+        let no_key = "no key configured (set key_file or TODO_PASSPHRASE)";
+        match command {
+            DbCommand::Restore { path: backup } => {
+                let version = TodoDatabase::restore(path, backup, key).await?;
+                println!(
+                    "Restored {} from {} (schema version {})",
+                    database,
+                    backup.display(),
+                    version
+                );
+                return Ok(());
+            }
+            DbCommand::Encrypt(new) => {
+                let new = new
+                    .resolve()
+                    .or_else(|| key.cloned())
+                    .ok_or("no key given (use --key-file or TODO_NEW_PASSPHRASE)")?;
+                TodoDatabase::encrypt(path, &new).await?;
+                println!("Encrypted {}", database);
+                return Ok(());
+            }
+            DbCommand::Decrypt => {
+                TodoDatabase::decrypt(path, key.ok_or(no_key)?).await?;
+                println!("Decrypted {}", database);
+                return Ok(());
+            }
+            DbCommand::Rekey(new) => {
+                let new = new
+                    .resolve()
+                    .ok_or("no new key given (use --key-file or TODO_NEW_PASSPHRASE)")?;
+                TodoDatabase::rekey(path, key.ok_or(no_key)?, &new).await?;
+                println!("Changed the key of {}", database);
+                return Ok(());
+            }
+            DbCommand::Backup { .. } | DbCommand::Check => {}
+        }
+    }
+
+    let mut db = TodoDatabase::open(&database, config.database_options).await?;
+    if let Some(identity) = cli.identity.or(config.identity) {
+        db = db.with_actor(identity);
+    }
+    // Closing checkpoints the WAL and removes the -wal and -shm files, so it
+    // must happen however the command ends
+    let result = async {
+        let rules = cli.transitions.unwrap_or(config.transitions);
+        // Backups and checks must see the database as it is
+        let maintenance = matches!(cli.command, Some(Commands::Db { .. }));
+        if let Some(age) = cli
+            .auto_archive
+            .or(config.auto_archive)
+            .filter(|_| !maintenance)
+        {
+            let cutoff = age_cutoff(age)?.with_timezone(&Local).date_naive();
+            db.archive_completed(Some(cutoff)).await?;
+        }
+
+        match cli.command {
+            Some(Commands::Tui {
+                confirm,
+                sort,
+                board,
+                wip_limits,
+                r#where,
+            }) => {
+                let mut app = App::new(db.clone())
+                    .with_transition_rules(rules)
+                    .with_confirmation(confirm)
+                    .with_filter(config.filter)
+                    .with_sort(sort.unwrap_or(config.sort))
+                    .with_theme(config.theme)
+                    .with_keymap(config.keymap)
+                    .with_view(if board { View::Board } else { View::List });
+                for (status, limit) in config.wip_limits.into_iter().chain(wip_limits) {
+                    app = app.with_wip_limit(status, limit);
+                }
+                if let Some(query) = r#where {
+                    app = app.with_query(query);
+                }
+                app.run().await?;
+            }
+            None => {
+                // Default to TUI mode
+                let mut app = App::new(db.clone())
+                    .with_transition_rules(rules)
+                    .with_filter(config.filter)
+                    .with_sort(config.sort)
+                    .with_theme(config.theme)
+                    .with_keymap(config.keymap);
+                for (status, limit) in config.wip_limits {
+                    app = app.with_wip_limit(status, limit);
+                }
+                app.run().await?;
+            }
+            Some(Commands::List {
+                view,
+                completed,
+                pending,
+                status,
+                archived,
+                sort,
+                r#where,
+                limit,
+                offset,
+            }) => {
+                let statuses: &[Status] = match status {
+                    Some(ref status) => std::slice::from_ref(status),
+                    None if completed => &[Status::Done],
+                    None if pending => &Status::OPEN,
+                    None => &[],
+                };
+                let sort = sort.unwrap_or(config.sort);
+                let mut query = r#where;
+                if let Some(name) = view {
+                    let view = db
+                        .get_view(&name)
+                        .await?
+                        .ok_or_else(|| format!("no saved view named @{}", name))?;
+                    let saved = view.query.parse::<Query>()?;
+                    query = Some(match query {
+                        Some(query) => saved.and(query),
+                        None => saved,
+                    });
+                }
+                let query = match query {
+                    Some(query) if !statuses.is_empty() => Some(Query::status(statuses).and(query)),
+                    None if !statuses.is_empty() => Some(Query::status(statuses)),
+                    query => query,
+                };
+
+                if archived {
+                    let limit = limit.unwrap_or(u32::MAX);
+                    let todos = db.list_archived_window(&sort, offset, limit).await?;
+                    print_todos(futures::stream::iter(todos.into_iter().map(Ok)), output).await?;
+                } else if limit.is_some() || offset > 0 {
+                    let limit = limit.unwrap_or(u32::MAX);
+                    let todos = db.list_window(query.as_ref(), &sort, offset, limit).await?;
+                    print_todos(futures::stream::iter(todos.into_iter().map(Ok)), output).await?;
+                } else {
+                    // Large lists are printed as they are read, a page at a time
+                    print_todos(db.stream_todos(query, sort), output).await?;
+                }
+            }
+            Some(Commands::Add {
+                title,
+                description,
+                due,
+                priority,
+                project,
+                tags,
+            }) => {
+                let mut todo = Todo::new(title, description);
+                if let Some(due) = due {
+                    todo.due_date = Some(parse_due_date(&due, Local::now().date_naive())?);
+                }
+                todo.priority = priority;
+                todo.project = project;
+                todo.retag(&tags, &[]);
+                db.create_todo(&todo).await?;
+                println!("Todo added: {}", todo.id);
+            }
+            Some(Commands::Due { id, date }) => {
+                if let Some(mut todo) = db.get_todo(&id).await? {
+                    let due = match date.as_str() {
+                        "none" => None,
+                        date => Some(parse_due_date(date, Local::now().date_naive())?),
+                    };
+                    todo.reschedule(due);
+                    db.update_todo(&todo).await?;
+                    match due {
+                        Some(due) => println!("Todo due {}: {}", due, todo.title),
+                        None => println!("Due date cleared: {}", todo.title),
+                    }
+                } else {
+                    eprintln!("Todo not found: {}", id);
+                }
+            }
+            Some(Commands::Complete { id }) => {
+                set_status(&db, &id, Status::Done, None, &rules).await?;
+            }
+            Some(Commands::Start { id }) => {
+                set_status(&db, &id, Status::InProgress, None, &rules).await?;
+            }
+            Some(Commands::Block { id, reason }) => {
+                set_status(&db, &id, Status::Blocked, reason, &rules).await?;
+            }
+            Some(Commands::SetStatus { id, status, reason }) => {
+                set_status(&db, &id, status, reason, &rules).await?;
+            }
+            Some(Commands::Delete { id }) => {
+                if let Some(todo) = db.get_todo(&id).await? {
+                    db.delete_todo(&id).await?;
+                    println!("Todo moved to trash: {}", todo.title);
+                } else {
+                    eprintln!("Todo not found: {}", id);
+                }
+            }
+            Some(Commands::Move { id, before, after }) => {
+                let placement = match (&before, &after) {
+                    (Some(target), _) => Placement::Before(target),
+                    (None, Some(target)) => Placement::After(target),
+                    (None, None) => unreachable!("clap requires --before or --after"),
+                };
+                db.move_todo(&id, placement).await?;
+                println!("Todo moved: {}", id);
+            }
+            Some(Commands::Archive {
+                id,
+                completed_before,
+            }) => match id {
+                Some(id) => {
+                    if db.archive_todo(&id).await? {
+                        println!("Todo archived: {}", id);
+                    } else {
+                        eprintln!("Todo not found or not done/cancelled: {}", id);
+                    }
+                }
+                None => {
+                    let before = completed_before
+                        .map(|date| parse_due_date(&date, Local::now().date_naive()))
+                        .transpose()?;
+                    let count = db.archive_completed(before).await?;
+                    println!("Archived {} todos", count);
+                }
+            },
+            Some(Commands::Unarchive { id }) => {
+                if db.unarchive_todo(&id).await? {
+                    println!("Todo unarchived: {}", id);
+                } else {
+                    eprintln!("Todo not archived: {}", id);
+                }
+            }
+            Some(Commands::View { command }) => match command {
+                ViewCommand::Save { name, query } => {
+                    let view = db
+                        .save_view(parse_view_name(&name)?.as_str(), &query)
+                        .await?;
+                    println!("Saved view @{}: {}", view.name, view.query);
+                }
+                ViewCommand::List => {
+                    let views = db.list_views().await?;
+                    if output == OutputFormat::Json {
+                        return print_json(&views);
+                    }
+                    if views.is_empty() {
+                        println!("No saved views");
+                    }
+                    let width = views.iter().map(|v| v.name.len() + 1).max().unwrap_or(0);
+                    for view in views {
+                        println!(
+                            "{:width$}  {}",
+                            format!("@{}", view.name),
+                            view.query,
+                            width = width
+                        );
+                    }
+                }
+                ViewCommand::Delete { name } => {
+                    if db.delete_view(&name).await? {
+                        println!("Deleted view @{}", name);
+                    } else {
+                        eprintln!("No saved view named @{}", name);
+                    }
+                }
+            },
+            Some(Commands::Trash { command }) => match command {
+                TrashCommand::List => {
+                    let todos = db.list_trash().await?;
+                    if output == OutputFormat::Json {
+                        return print_json(&todos);
+                    }
+                    if todos.is_empty() {
+                        println!("Trash is empty");
+                    }
+                    for todo in todos {
+                        let deleted = todo.deleted_at.map(|d| d.with_timezone(&Local));
+                        println!(
+                            "{} {} - {} (deleted {})",
+                            todo.status.symbol(),
+                            todo.title,
+                            todo.id,
+                            deleted
+                                .map_or(String::new(), |d| d.format("%Y-%m-%d %H:%M").to_string())
+                        );
+                    }
+                }
+                TrashCommand::Restore { id } => {
+                    if db.restore_todo(&id).await? {
+                        println!("Todo restored: {}", id);
+                    } else {
+                        eprintln!("Todo not in trash: {}", id);
+                    }
+                }
+                TrashCommand::Empty { older_than } => {
+                    let cutoff = older_than.map(age_cutoff).transpose()?;
+                    let count = db.empty_trash(cutoff).await?;
+                    println!("Permanently deleted {} todos", count);
+                }
+            },
+            Some(Commands::History { id }) => {
+                let events = db.todo_events(&id).await?;
+                if output == OutputFormat::Json {
+                    return print_json(&events);
+                }
+                if events.is_empty() {
+                    eprintln!("No history for todo: {}", id);
+                }
+                if let Some(todo) = db.get_todo(&id).await? {
+                    println!("{} {}", todo.status.symbol(), todo.title);
+                }
+                for event in events {
+                    println!(
+                        "  {}  {}",
+                        event
+                            .created_at
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M"),
+                        event
+                    );
+                }
+            }
+            Some(Commands::Log { since, actor }) => {
+                let entries = db.audit_log(since, actor.as_deref()).await?;
+                if output == OutputFormat::Json {
+                    return print_json(&entries);
+                }
+                if entries.is_empty() {
+                    println!("No changes found.");
+                }
+                for entry in entries {
+                    println!(
+                        "{}  {:<12} {:<9} {}  {}",
+                        entry
+                            .created_at
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M:%S"),
+                        entry.actor,
+                        entry.action,
+                        entry.todo_id,
+                        entry.diff
+                    );
+                }
+            }
+            Some(Commands::Db { command }) => match command {
+                DbCommand::Backup { path } => {
+                    db.backup(&path).await?;
+                    println!("Backed up {} to {}", database, path.display());
+                }
+                DbCommand::Check => {
+                    let problems = db.check().await?;
+                    if problems.is_empty() {
+                        println!("No problems found in {}", database);
+                    } else {
+                        for problem in &problems {
+                            println!("{}", problem);
+                        }
+                        return Err(
+                            format!("{} problems found in {}", problems.len(), database).into()
+                        );
+                    }
+                }
+                DbCommand::Restore { .. }
+                | DbCommand::Encrypt(_)
+                | DbCommand::Decrypt
+                | DbCommand::Rekey(_) => unreachable!("handled before opening the database"),
+            },
+            Some(Commands::Config { .. }) | Some(Commands::Where) => {
+                unreachable!("handled before opening the database")
+            }
+            Some(Commands::Stats { days }) => {
+                let stats = db.stats(days, Local::now().date_naive()).await?;
+                println!(
+                    "{} todos, {:.0}% done",
+                    stats.total,
+                    stats.completion_rate() * 100.0
+                );
+                for (status, count) in &stats.by_status {
+                    println!("  {} {:<12} {:>5}", status.symbol(), status.label(), count);
+                }
+                if let Some(average) = stats.average_completion_time {
+                    println!("Average time to complete: {}", format_duration(average));
+                }
+
+                let completed: Vec<i64> = stats.per_day.iter().map(|p| p.completed).collect();
+                let total: i64 = completed.iter().sum();
+                println!(
+                    "\nCompleted in the last {} days: {} ({:.1}/day)",
+                    stats.per_day.len(),
+                    total,
+                    total as f64 / stats.per_day.len() as f64
+                );
+                println!("  {}", sparkline(&completed));
+
+                println!("\nPer week:");
+                for week in &stats.per_week {
+                    println!(
+                        "  {}  created {:>3}  completed {:>3}",
+                        week.start, week.created, week.completed
+                    );
+                }
+
+                if !stats.oldest_open.is_empty() {
+                    println!("\nOldest open:");
+                    for todo in &stats.oldest_open {
+                        println!(
+                            "  {} {} - {} (created {})",
+                            todo.status.symbol(),
+                            todo.title,
+                            todo.id,
+                            todo.created_at.format("%Y-%m-%d")
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+    .await;
+    db.close().await;
+    result
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Kind of mutation recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    /// Moved to the trash
+    Delete,
+    Restore,
+    /// Removed from the trash for good
+    Purge,
+    Archive,
+    Unarchive,
+    /// Moved in manual order
+    Move,
+}
+
+impl AuditAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+            AuditAction::Archive => "archive",
+            AuditAction::Unarchive => "unarchive",
+            AuditAction::Move => "move",
+        }
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// One mutation of a todo: who made it, when, and what changed
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub action: AuditAction,
+    pub todo_id: String,
+    /// JSON object `{"before": ..., "after": ...}` holding the changed fields,
+    /// or the whole todo when it was created or purged
+    pub diff: String,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,97 @@
+use crate::Result;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// Parse a due date: `YYYY-MM-DD`, `today`, `tomorrow`, `+Nd`/`+Nw`, or a weekday
+/// name meaning its next occurrence after `today`
+pub fn parse_due_date(input: &str, today: NaiveDate) -> Result<NaiveDate> {
+    let input = input.trim().to_ascii_lowercase();
+    match input.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return Ok(today + Duration::days(1)),
+        _ => {}
+    }
+    if let Some(offset) = input.strip_prefix('+') {
+        let invalid = || anyhow::anyhow!("invalid date offset: {}", input);
+        let (count, unit) = match offset.char_indices().last() {
+            Some((index, unit)) => (&offset[..index], unit),
+            None => return Err(invalid()),
+        };
+        let count: i64 = count.parse().map_err(|_| invalid())?;
+        let offset = match unit {
+            'd' => Duration::try_days(count),
+            'w' => Duration::try_weeks(count),
+            _ => return Err(invalid()),
+        };
+        return offset
+            .and_then(|offset| today.checked_add_signed(offset))
+            .ok_or_else(|| anyhow::anyhow!("date offset out of range: {}", input));
+    }
+    if let Ok(weekday) = input.parse::<Weekday>() {
+        return Ok(next_weekday(today, weekday));
+    }
+    NaiveDate::parse_from_str(&input, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("invalid date: {} (expected YYYY-MM-DD)", input))
+}
+
+/// Parse an age such as `30d`, `2w` or `12h`
+pub fn parse_age(input: &str) -> Result<Duration> {
+    let input = input.trim().to_ascii_lowercase();
+    let invalid = || anyhow::anyhow!("invalid age: {} (expected e.g. 30d, 2w or 12h)", input);
+    let (count, unit) = match input.char_indices().last() {
+        Some((index, unit)) => (&input[..index], unit),
+        None => return Err(invalid()),
+    };
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    let age = match unit {
+        'h' => Duration::try_hours(count),
+        'd' => Duration::try_days(count),
+        'w' => Duration::try_weeks(count),
+        _ => return Err(invalid()),
+    };
+    age.ok_or_else(|| anyhow::anyhow!("age out of range: {}", input))
+}
+
+/// The first `weekday` strictly after `date`
+pub fn next_weekday(date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let days = (7 + weekday.num_days_from_monday() as i64
+        - date.weekday().num_days_from_monday() as i64)
+        % 7;
+    date + Duration::days(if days == 0 { 7 } else { days })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_due_date() {
+        // 2026-10-14 is a Wednesday
+        let today = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+        assert_eq!(parse_due_date("today", today).unwrap(), today);
+        assert_eq!(parse_due_date("tomorrow", today).unwrap(), date(15));
+        assert_eq!(parse_due_date("+3d", today).unwrap(), date(17));
+        assert_eq!(parse_due_date("+1w", today).unwrap(), date(21));
+        assert_eq!(parse_due_date("monday", today).unwrap(), date(19));
+        assert_eq!(parse_due_date("wed", today).unwrap(), date(21));
+        assert_eq!(parse_due_date("2026-10-30", today).unwrap(), date(30));
+        assert!(parse_due_date("soon", today).is_err());
+        assert!(parse_due_date("+xd", today).is_err());
+        assert!(parse_due_date("+", today).is_err());
+        assert!(parse_due_date("+1日", today).is_err());
+        assert!(parse_due_date("+99999999999d", today).is_err());
+        assert!(parse_due_date("+9999999999999999w", today).is_err());
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_age("2W").unwrap(), Duration::weeks(2));
+        assert_eq!(parse_age("12h").unwrap(), Duration::hours(12));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("").is_err());
+        assert!(parse_age("3日").is_err());
+        assert!(parse_age("9999999999999w").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Kind of change recorded in a todo's history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum EventKind {
+    Created,
+    /// Status changed; the values are status names
+    Status,
+    /// Title changed
+    Renamed,
+    /// Description changed
+    Described,
+    /// Due date set, changed or cleared
+    Rescheduled,
+    /// Moved to the trash
+    Deleted,
+    /// Taken back out of the trash
+    Restored,
+    /// Removed from the trash for good
+    Purged,
+    /// Hidden from the normal lists once finished
+    Archived,
+    /// Taken back out of the archive
+    Unarchived,
+}
+
+impl EventKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            EventKind::Created => "created",
+            EventKind::Status => "status",
+            EventKind::Renamed => "renamed",
+            EventKind::Described => "described",
+            EventKind::Rescheduled => "rescheduled",
+            EventKind::Deleted => "deleted",
+            EventKind::Restored => "restored",
+            EventKind::Purged => "purged",
+            EventKind::Archived => "archived",
+            EventKind::Unarchived => "unarchived",
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// One entry in the append-only history of a todo
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TodoEvent {
+    pub id: i64,
+    pub todo_id: String,
+    pub kind: EventKind,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    /// Status reason given with a status change
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl fmt::Display for TodoEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "none".to_string());
+        match self.kind {
+            EventKind::Created
+            | EventKind::Deleted
+            | EventKind::Restored
+            | EventKind::Purged
+            | EventKind::Archived
+            | EventKind::Unarchived => write!(f, "{}", self.kind)?,
+            _ => write!(
+                f,
+                "{}: {} -> {}",
+                self.kind,
+                value(&self.old_value),
+                value(&self.new_value)
+            )?,
+        }
+        if let Some(note) = &self.note {
+            write!(f, " ({})", note)?;
+        }
+        Ok(())
+    }
+}
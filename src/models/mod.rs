@@ -1,4 +1,19 @@
-use chrono::{DateTime, Utc};
+mod audit;
+mod due;
+mod event;
+mod priority;
+mod status;
+mod view;
+
+pub use audit::{AuditAction, AuditEntry};
+pub use due::{next_weekday, parse_age, parse_due_date};
+pub use event::{EventKind, TodoEvent};
+pub use priority::Priority;
+pub use status::{Status, TransitionRules};
+pub use view::SavedView;
+
+use crate::Result;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -8,9 +23,30 @@ pub struct Todo {
     pub id: String,
     pub title: String,
     pub description: Option<String>,
-    pub completed: bool,
+    pub status: Status,
+    /// Why the todo is blocked or waiting
+    pub status_reason: Option<String>,
+    pub priority: Option<Priority>,
+    /// Name of the project the todo belongs to
+    pub project: Option<String>,
+    /// Labels, kept sorted and without duplicates
+    #[serde(default)]
+    #[sqlx(json)]
+    pub tags: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Rank in manual order; `None` until the todo has been stored
+    pub position: Option<f64>,
+    pub due_date: Option<NaiveDate>,
+    /// When the todo was last marked done; `None` while it is not done
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the todo was moved to the trash; `None` unless it is deleted
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// When the finished todo was archived; `None` unless it is archived
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Incremented on every stored update; updates must name the version they
+    /// are based on, see [`crate::database::Conflict`]
+    pub version: i64,
 }
 
 impl Todo {
@@ -21,21 +57,97 @@ impl Todo {
             id: Uuid::new_v4().to_string(),
             title,
             description,
-            completed: false,
+            status: Status::Todo,
+            status_reason: None,
+            priority: None,
+            project: None,
+            tags: Vec::new(),
             created_at: now,
             updated_at: now,
+            position: None,
+            due_date: None,
+            completed_at: None,
+            deleted_at: None,
+            archived_at: None,
+            version: 1,
         }
     }
 
     /// Mark todo as completed
     pub fn complete(&mut self) {
-        self.completed = true;
-        self.updated_at = Utc::now();
+        self.set_status(Status::Done);
     }
 
     /// Mark todo as incomplete
     pub fn uncomplete(&mut self) {
-        self.completed = false;
+        self.set_status(Status::Todo);
+    }
+
+    /// Whether the todo is done
+    pub fn is_completed(&self) -> bool {
+        self.status == Status::Done
+    }
+
+    /// Change the workflow status, clearing any previous reason; reopening
+    /// takes the todo out of the archive
+    pub fn set_status(&mut self, status: Status) {
+        let now = Utc::now();
+        if status != Status::Done {
+            self.completed_at = None;
+        } else if self.status != Status::Done {
+            self.completed_at = Some(now);
+        }
+        if !status.is_closed() {
+            self.archived_at = None;
+        }
+        self.status = status;
+        self.status_reason = None;
+        self.updated_at = now;
+    }
+
+    /// Change the workflow status if `rules` allow it, recording an optional reason
+    pub fn transition(
+        &mut self,
+        status: Status,
+        reason: Option<String>,
+        rules: &TransitionRules,
+    ) -> Result<()> {
+        if !rules.allows(self.status, status) {
+            anyhow::bail!(
+                "cannot change status from {} to {}",
+                self.status.label(),
+                status.label()
+            );
+        }
+        self.set_status(status);
+        self.status_reason = reason;
+        Ok(())
+    }
+
+    /// Set or clear the due date
+    pub fn reschedule(&mut self, due_date: Option<NaiveDate>) {
+        self.due_date = due_date;
+        self.updated_at = Utc::now();
+    }
+
+    /// Set or clear the priority
+    pub fn prioritize(&mut self, priority: Option<Priority>) {
+        self.priority = priority;
+        self.updated_at = Utc::now();
+    }
+
+    /// Move the todo to a project, or out of any with `None`
+    pub fn move_to_project(&mut self, project: Option<String>) {
+        self.project = project;
+        self.updated_at = Utc::now();
+    }
+
+    /// Add `add` to the tags and take `remove` off them
+    pub fn retag(&mut self, add: &[String], remove: &[String]) {
+        self.tags.retain(|tag| !remove.contains(tag));
+        self.tags.extend(add.iter().cloned());
+        self.tags.sort();
+        self.tags.dedup();
         self.updated_at = Utc::now();
     }
 
@@ -60,7 +172,7 @@ mod tests {
         let todo = Todo::new("Test".to_string(), Some("Description".to_string()));
         assert_eq!(todo.title, "Test");
         assert_eq!(todo.description, Some("Description".to_string()));
-        assert!(!todo.completed);
+        assert!(!todo.is_completed());
         assert!(!todo.id.is_empty());
     }
 
@@ -68,7 +180,7 @@ mod tests {
     fn test_complete_todo() {
         let mut todo = Todo::new("Test".to_string(), None);
         todo.complete();
-        assert!(todo.completed);
+        assert!(todo.is_completed());
     }
 
     #[test]
@@ -76,7 +188,47 @@ mod tests {
         let mut todo = Todo::new("Test".to_string(), None);
         todo.complete();
         todo.uncomplete();
-        assert!(!todo.completed);
+        assert!(!todo.is_completed());
+    }
+
+    #[test]
+    fn test_set_status() {
+        let mut todo = Todo::new("Test".to_string(), None);
+        todo.set_status(Status::InProgress);
+        assert_eq!(todo.status, Status::InProgress);
+        assert!(!todo.is_completed());
+        todo.complete();
+        assert_eq!(todo.status, Status::Done);
+        assert!(todo.is_completed());
+        let completed_at = todo.completed_at.expect("completed_at set");
+        todo.complete();
+        assert_eq!(todo.completed_at, Some(completed_at));
+        todo.uncomplete();
+        assert!(todo.completed_at.is_none());
+    }
+
+    #[test]
+    fn test_transition() {
+        let mut todo = Todo::new("Test".to_string(), None);
+        let rules: TransitionRules = "todo:blocked".parse().unwrap();
+        todo.transition(
+            Status::Blocked,
+            Some("waiting on review".to_string()),
+            &rules,
+        )
+        .unwrap();
+        assert_eq!(todo.status, Status::Blocked);
+        assert_eq!(todo.status_reason.as_deref(), Some("waiting on review"));
+        assert!(todo.transition(Status::Done, None, &rules).is_err());
+        assert_eq!(todo.status, Status::Blocked);
+    }
+
+    #[test]
+    fn test_retag() {
+        let mut todo = Todo::new("Test".to_string(), None);
+        todo.retag(&["work".to_string(), "home".to_string()], &[]);
+        todo.retag(&["work".to_string()], &["home".to_string()]);
+        assert_eq!(todo.tags, vec!["work".to_string()]);
     }
 
     #[test]
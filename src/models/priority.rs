@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How urgent a todo is
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    /// Name used on the command line and in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+
+    /// Short marker used in lists
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Priority::Low => "!",
+            Priority::Medium => "!!",
+            Priority::High => "!!!",
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Priority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "low" | "l" => Ok(Priority::Low),
+            "medium" | "med" | "m" => Ok(Priority::Medium),
+            "high" | "h" => Ok(Priority::High),
+            other => anyhow::bail!("unknown priority: {} (expected low, medium or high)", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_priority() {
+        assert_eq!("High".parse::<Priority>().unwrap(), Priority::High);
+        assert_eq!("med".parse::<Priority>().unwrap(), Priority::Medium);
+        assert!("urgent".parse::<Priority>().is_err());
+        assert!(Priority::Low < Priority::High);
+    }
+}
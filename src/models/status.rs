@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// Workflow status of a todo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Status {
+    #[default]
+    Todo,
+    InProgress,
+    Blocked,
+    Waiting,
+    Done,
+    Cancelled,
+}
+
+impl Status {
+    /// All statuses in workflow order
+    pub const ALL: [Status; 6] = [
+        Status::Todo,
+        Status::InProgress,
+        Status::Blocked,
+        Status::Waiting,
+        Status::Done,
+        Status::Cancelled,
+    ];
+
+    /// Statuses shown as columns on the TUI board
+    pub const BOARD: [Status; 5] = [
+        Status::Todo,
+        Status::InProgress,
+        Status::Blocked,
+        Status::Waiting,
+        Status::Done,
+    ];
+
+    /// Statuses of todos that still need work
+    pub const OPEN: [Status; 4] = [
+        Status::Todo,
+        Status::InProgress,
+        Status::Blocked,
+        Status::Waiting,
+    ];
+
+    /// Human readable name
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Todo => "Todo",
+            Status::InProgress => "In Progress",
+            Status::Blocked => "Blocked",
+            Status::Waiting => "Waiting",
+            Status::Done => "Done",
+            Status::Cancelled => "Cancelled",
+        }
+    }
+
+    /// Name used on the command line and in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Todo => "todo",
+            Status::InProgress => "in_progress",
+            Status::Blocked => "blocked",
+            Status::Waiting => "waiting",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+        }
+    }
+
+    /// Single character marker used in lists
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Status::Todo => "○",
+            Status::InProgress => "◐",
+            Status::Blocked => "■",
+            Status::Waiting => "…",
+            Status::Done => "✓",
+            Status::Cancelled => "✗",
+        }
+    }
+
+    /// Whether no more work is expected (done or cancelled)
+    pub fn is_closed(self) -> bool {
+        matches!(self, Status::Done | Status::Cancelled)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Status {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', ' '], "_").as_str() {
+            "todo" | "open" => Ok(Status::Todo),
+            "in_progress" | "doing" | "started" => Ok(Status::InProgress),
+            "blocked" => Ok(Status::Blocked),
+            "waiting" => Ok(Status::Waiting),
+            "done" | "completed" => Ok(Status::Done),
+            "cancelled" | "canceled" => Ok(Status::Cancelled),
+            other => anyhow::bail!("unknown status: {}", other),
+        }
+    }
+}
+
+/// Allowed status transitions
+///
+/// The default allows every transition. Restricted rules are written as
+/// `from:to|to;from:to`, e.g. `todo:in_progress|cancelled;in_progress:done`;
+/// statuses without an entry may not change at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransitionRules {
+    /// `None` allows everything
+    allowed: Option<HashMap<Status, HashSet<Status>>>,
+}
+
+impl TransitionRules {
+    /// Rules allowing any transition
+    pub fn permissive() -> Self {
+        Self::default()
+    }
+
+    /// Whether a todo may move from `from` to `to`
+    pub fn allows(&self, from: Status, to: Status) -> bool {
+        if from == to {
+            return true;
+        }
+        match &self.allowed {
+            None => true,
+            Some(allowed) => allowed
+                .get(&from)
+                .is_some_and(|to_set| to_set.contains(&to)),
+        }
+    }
+}
+
+impl FromStr for TransitionRules {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut allowed: HashMap<Status, HashSet<Status>> = HashMap::new();
+        for rule in s.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (from, to) = rule
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("expected FROM:TO|TO, got '{}'", rule))?;
+            let from: Status = from.trim().parse()?;
+            let targets = allowed.entry(from).or_default();
+            for to in to.split('|').map(str::trim).filter(|t| !t.is_empty()) {
+                targets.insert(to.parse()?);
+            }
+        }
+        Ok(Self {
+            allowed: Some(allowed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!("in-progress".parse::<Status>().unwrap(), Status::InProgress);
+        assert_eq!("Canceled".parse::<Status>().unwrap(), Status::Cancelled);
+        assert!("later".parse::<Status>().is_err());
+    }
+
+    #[test]
+    fn test_transition_rules() {
+        assert!(TransitionRules::permissive().allows(Status::Done, Status::Blocked));
+
+        let rules: TransitionRules = "todo:in_progress|cancelled; in_progress:done|blocked"
+            .parse()
+            .unwrap();
+        assert!(rules.allows(Status::Todo, Status::InProgress));
+        assert!(rules.allows(Status::InProgress, Status::Done));
+        assert!(!rules.allows(Status::Todo, Status::Done));
+        assert!(!rules.allows(Status::Done, Status::Todo));
+        assert!(rules.allows(Status::Done, Status::Done));
+        assert!("todo".parse::<TransitionRules>().is_err());
+    }
+}
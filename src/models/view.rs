@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A named filter query, run with `list @name` or picked in the TUI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SavedView {
+    pub name: String,
+    /// Query source as entered, so relative dates such as `today` stay relative
+    pub query: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SavedView {
+    /// Check that `name` can be written as `@name` on the command line
+    pub fn validate_name(name: &str) -> crate::Result<()> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            anyhow::bail!(
+                "invalid view name: {:?} (use letters, digits, '-', '_' and '.')",
+                name
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use super::{App, Operation};
+use crate::models::{Status, Todo};
+use crate::Result;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+
+impl App {
+    /// Todos in the given board column, in list order
+    pub(super) fn column_todos(&self, status: Status) -> Vec<&Todo> {
+        self.todos.iter().filter(|t| t.status == status).collect()
+    }
+
+    /// The card under the cursor in the focused column
+    pub(super) fn selected_card(&self) -> Option<&Todo> {
+        let status = Status::BOARD[self.board_column];
+        self.column_todos(status)
+            .get(self.board_rows[self.board_column])
+            .copied()
+    }
+
+    /// Handle board-specific keys, returning whether the key was consumed
+    pub(super) async fn handle_board_input(&mut self, key: KeyCode) -> Result<bool> {
+        match key {
+            KeyCode::Left => self.focus_column(self.board_column.saturating_sub(1)),
+            KeyCode::Right => {
+                self.focus_column((self.board_column + 1).min(Status::BOARD.len() - 1))
+            }
+            KeyCode::Up => self.scroll_column(-1),
+            KeyCode::Down => self.scroll_column(1),
+            KeyCode::Char('h') => self.move_card(-1).await?,
+            KeyCode::Char('l') => self.move_card(1).await?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn focus_column(&mut self, column: usize) {
+        self.board_column = column;
+        self.clamp_board_rows();
+    }
+
+    pub(super) fn scroll_column(&mut self, delta: isize) {
+        let len = self.column_todos(Status::BOARD[self.board_column]).len();
+        let row = &mut self.board_rows[self.board_column];
+        *row = row.saturating_add_signed(delta).min(len.saturating_sub(1));
+    }
+
+    /// Keep every column's cursor within its number of cards
+    pub(super) fn clamp_board_rows(&mut self) {
+        for (column, status) in Status::BOARD.into_iter().enumerate() {
+            let len = self.column_todos(status).len();
+            self.board_rows[column] = self.board_rows[column].min(len.saturating_sub(1));
+        }
+    }
+
+    /// Move the selected card one column left (`-1`) or right (`1`)
+    async fn move_card(&mut self, delta: isize) -> Result<()> {
+        let Some(before) = self.selected_card().cloned() else {
+            return Ok(());
+        };
+        let Some(target) = self
+            .board_column
+            .checked_add_signed(delta)
+            .filter(|&c| c < Status::BOARD.len())
+        else {
+            return Ok(());
+        };
+        let status = Status::BOARD[target];
+        if let Some(&limit) = self.wip_limits.get(&status) {
+            if self.column_todos(status).len() >= limit {
+                self.status_message = format!(
+                    "WIP limit reached: {} allows {} todos",
+                    status.label(),
+                    limit
+                );
+                return Ok(());
+            }
+        }
+
+        let mut after = before.clone();
+        if let Err(e) = after.transition(status, None, &self.transition_rules) {
+            self.status_message = e.to_string();
+            return Ok(());
+        }
+        self.save_todos(vec![after.clone()]);
+        let id = after.id.clone();
+        self.history.record(Operation::SetStatus { before, after });
+
+        self.board_column = target;
+        if let Some(row) = self.column_todos(status).iter().position(|t| t.id == id) {
+            self.board_rows[target] = row;
+        }
+        self.status_message = format!("Moved to {}", status.label());
+        Ok(())
+    }
+
+    pub(super) fn render_board(&self, f: &mut Frame, area: Rect) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, Status::BOARD.len() as u32); Status::BOARD.len()])
+            .split(area);
+
+        for (column, status) in Status::BOARD.into_iter().enumerate() {
+            let cards = self.column_todos(status);
+            let focused = column == self.board_column;
+
+            let (title, over_limit) = match self.wip_limits.get(&status) {
+                Some(&limit) => (
+                    format!("{} ({}/{})", status.label(), cards.len(), limit),
+                    cards.len() > limit,
+                ),
+                None => (format!("{} ({})", status.label(), cards.len()), false),
+            };
+            let border_style = if over_limit {
+                Style::default().fg(Color::Red)
+            } else if focused {
+                Style::default().fg(self.theme.accent())
+            } else {
+                Style::default()
+            };
+
+            let items: Vec<ListItem> = cards
+                .iter()
+                .map(|todo| {
+                    let style = if todo.status == Status::Done {
+                        Style::default()
+                            .fg(Color::Green)
+                            .add_modifier(Modifier::CROSSED_OUT)
+                    } else {
+                        Style::default().fg(self.theme.text())
+                    };
+                    let content = match &todo.status_reason {
+                        Some(reason) => format!("{} ({})", todo.title, reason),
+                        None => todo.title.clone(),
+                    };
+                    ListItem::new(content).style(style)
+                })
+                .collect();
+
+            let list = List::new(items)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(border_style)
+                        .title(title),
+                )
+                .highlight_style(Style::default().bg(self.theme.highlight()))
+                .highlight_symbol(">> ");
+
+            let mut state = ListState::default();
+            if focused && !cards.is_empty() {
+                state.select(Some(self.board_rows[column]));
+            }
+            f.render_stateful_widget(list, columns[column], &mut state);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use super::{App, InputMode, Operation};
+use crate::models::{Priority, Todo};
+use crate::Result;
+use crossterm::event::KeyCode;
+
+/// Field a bulk action typed into the status bar sets on the targeted todos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Tags,
+    Project,
+    Priority,
+}
+
+impl Field {
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Field::Tags => "Tags",
+            Field::Project => "Project",
+            Field::Priority => "Priority",
+        }
+    }
+}
+
+impl App {
+    /// Ask for the value of `field` to set on the marked or selected todos
+    pub(super) fn start_assign(&mut self, field: Field) {
+        let count = self.targets().len();
+        if count == 0 {
+            return;
+        }
+        let hint = match field {
+            Field::Tags => "tag or +tag to add, -tag to remove",
+            Field::Project => "name, empty for none",
+            Field::Priority => "low, medium, high, empty for none",
+        };
+        self.input.clear();
+        self.input_mode = InputMode::Assigning(field);
+        self.status_message = format!(
+            "{} for {} todo{} ({}; Enter to apply, ESC to cancel):",
+            field.label(),
+            count,
+            if count == 1 { "" } else { "s" },
+            hint
+        );
+    }
+
+    pub(super) async fn handle_assign_input(&mut self, field: Field, key: KeyCode) -> Result<bool> {
+        match key {
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.input);
+                self.input_mode = InputMode::Normal;
+                if let Err(err) = self.assign(field, input.trim()) {
+                    self.status_message = format!("Invalid {}: {}", field.label(), err);
+                }
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Esc => {
+                self.input.clear();
+                self.input_mode = InputMode::Normal;
+                self.status_message = "Cancelled".to_string();
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Set `field` from `input` on the marked or selected todos, in a single
+    /// transaction and as a single undoable operation
+    fn assign(&mut self, field: Field, input: &str) -> Result<()> {
+        let change: Box<dyn Fn(&mut Todo)> = match field {
+            Field::Tags => {
+                let (mut add, mut remove) = (Vec::new(), Vec::new());
+                for word in input.split_whitespace() {
+                    match word.strip_prefix('-') {
+                        Some(tag) => remove.push(tag.to_string()),
+                        None => add.push(word.trim_start_matches('+').to_string()),
+                    }
+                }
+                if add.iter().chain(&remove).any(String::is_empty) {
+                    anyhow::bail!("empty tag");
+                }
+                Box::new(move |todo| todo.retag(&add, &remove))
+            }
+            Field::Project => {
+                let project = Some(input.to_string()).filter(|p| !p.is_empty());
+                Box::new(move |todo| todo.move_to_project(project.clone()))
+            }
+            Field::Priority => {
+                let priority = match input {
+                    "" | "none" => None,
+                    priority => Some(priority.parse::<Priority>()?),
+                };
+                Box::new(move |todo| todo.prioritize(priority))
+            }
+        };
+
+        let mut ops = Vec::new();
+        let mut updated = Vec::new();
+        for before in self.targets() {
+            let mut after = before.clone();
+            change(&mut after);
+            if (&after.tags, &after.project, after.priority)
+                == (&before.tags, &before.project, before.priority)
+            {
+                continue;
+            }
+            updated.push(after.clone());
+            ops.push(Operation::Edit { before, after });
+        }
+
+        let count = ops.len();
+        self.status_message = match count {
+            0 => "Nothing to change".to_string(),
+            1 => format!("{} updated!", field.label()),
+            _ => format!("{} updated on {} todos!", field.label(), count),
+        };
+        if count == 0 {
+            return Ok(());
+        }
+        let op = if count == 1 {
+            ops.remove(0)
+        } else {
+            Operation::Bulk(ops)
+        };
+        self.save_todos(updated);
+        self.history.record(op);
+        self.marked.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TodoDatabase;
+
+    #[tokio::test]
+    async fn test_bulk_assign_and_undo() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todos: Vec<Todo> = (0..3).map(|i| Todo::new(i.to_string(), None)).collect();
+        for todo in &todos {
+            db.create_todo(todo).await.unwrap();
+        }
+
+        let mut app = App::new(db.clone());
+        app.refresh_todos().await.unwrap();
+        app.marked = todos[..2].iter().map(|t| t.id.clone()).collect();
+        app.assign(Field::Tags, "work +urgent").unwrap();
+        app.marked = todos[..2].iter().map(|t| t.id.clone()).collect();
+        app.assign(Field::Priority, "high").unwrap();
+        app.settle().await;
+        assert!(app.assign(Field::Priority, "someday").is_err());
+
+        for todo in &todos[..2] {
+            let stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+            assert_eq!(stored.tags, vec!["urgent".to_string(), "work".to_string()]);
+            assert_eq!(stored.priority, Some(Priority::High));
+        }
+        let untouched = db.get_todo(&todos[2].id).await.unwrap().unwrap();
+        assert!(untouched.tags.is_empty());
+
+        app.undo();
+        app.settle().await;
+        let stored = db.get_todo(&todos[0].id).await.unwrap().unwrap();
+        assert_eq!(stored.priority, None);
+        assert_eq!(stored.tags.len(), 2);
+    }
+}
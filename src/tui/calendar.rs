@@ -0,0 +1,257 @@
+use super::{App, Operation, Theme, View};
+use crate::models::{next_weekday, Status, Todo};
+use crate::Result;
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+/// Number of weeks shown in the month grid
+const GRID_WEEKS: i64 = 6;
+
+const WEEKDAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+fn todo_style(todo: &Todo, theme: Theme) -> Style {
+    match todo.status {
+        Status::Done | Status::Cancelled => Style::default()
+            .fg(theme.muted())
+            .add_modifier(Modifier::CROSSED_OUT),
+        Status::Blocked | Status::Waiting => Style::default().fg(Color::Red),
+        _ => Style::default().fg(theme.text()),
+    }
+}
+
+impl App {
+    /// First and last day loaded for the current calendar view
+    pub(super) fn calendar_range(&self) -> (NaiveDate, NaiveDate) {
+        match self.view {
+            View::Week => {
+                let start = week_start(self.calendar_day);
+                (start, start + Duration::days(6))
+            }
+            _ => {
+                let first = self.calendar_day.with_day(1).unwrap_or(self.calendar_day);
+                let start = week_start(first);
+                (start, start + Duration::days(GRID_WEEKS * 7 - 1))
+            }
+        }
+    }
+
+    /// Todos due on `date`
+    fn day_todos(&self, date: NaiveDate) -> Vec<&Todo> {
+        self.todos
+            .iter()
+            .filter(|t| t.due_date == Some(date))
+            .collect()
+    }
+
+    /// The todo under the cursor on the selected day
+    pub(super) fn selected_day_todo(&self) -> Option<&Todo> {
+        self.day_todos(self.calendar_day)
+            .get(self.calendar_row)
+            .copied()
+    }
+
+    /// Handle calendar-specific keys, returning whether the key was consumed
+    pub(super) async fn handle_calendar_input(&mut self, key: KeyCode) -> Result<bool> {
+        match key {
+            KeyCode::Left => self.goto_day(self.calendar_day - Duration::days(1)).await?,
+            KeyCode::Right => self.goto_day(self.calendar_day + Duration::days(1)).await?,
+            KeyCode::Up => self.goto_day(self.calendar_day - Duration::days(7)).await?,
+            KeyCode::Down => self.goto_day(self.calendar_day + Duration::days(7)).await?,
+            KeyCode::Char('t') => self.goto_day(today()).await?,
+            KeyCode::Char('j') => self.scroll_day(1),
+            KeyCode::Char('k') => self.scroll_day(-1),
+            KeyCode::Char('+') => {
+                let date = self.calendar_day + Duration::days(1);
+                self.reschedule_selected(date)
+            }
+            KeyCode::Char('-') => {
+                let date = self.calendar_day - Duration::days(1);
+                self.reschedule_selected(date)
+            }
+            KeyCode::Char('N') => {
+                let date = next_weekday(self.calendar_day, Weekday::Mon);
+                self.reschedule_selected(date)
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Move the cursor to `date`, loading a new range if it falls outside the current one
+    async fn goto_day(&mut self, date: NaiveDate) -> Result<()> {
+        let range = self.calendar_range();
+        self.calendar_day = date;
+        self.calendar_row = 0;
+        if self.calendar_range() != range {
+            self.reload();
+        }
+        Ok(())
+    }
+
+    pub(super) fn scroll_day(&mut self, delta: isize) {
+        let len = self.day_todos(self.calendar_day).len();
+        self.calendar_row = self
+            .calendar_row
+            .saturating_add_signed(delta)
+            .min(len.saturating_sub(1));
+    }
+
+    /// Change the due date of the selected todo and follow it to its new day
+    fn reschedule_selected(&mut self, date: NaiveDate) {
+        let Some(before) = self.selected_day_todo().cloned() else {
+            return;
+        };
+        let mut after = before.clone();
+        after.reschedule(Some(date));
+        self.save_todos(vec![after.clone()]);
+        let id = after.id.clone();
+        self.history.record(Operation::Edit { before, after });
+
+        let range = self.calendar_range();
+        self.calendar_day = date;
+        if self.calendar_range() != range {
+            self.reload();
+        }
+        self.calendar_row = self
+            .day_todos(date)
+            .iter()
+            .position(|t| t.id == id)
+            .unwrap_or(0);
+        self.status_message = format!("Rescheduled to {}", date.format("%a %Y-%m-%d"));
+    }
+
+    pub(super) fn calendar_title(&self) -> String {
+        match self.view {
+            View::Week => format!(
+                " Week of {} ",
+                week_start(self.calendar_day).format("%Y-%m-%d")
+            ),
+            _ => format!(" {} ", self.calendar_day.format("%B %Y")),
+        }
+    }
+
+    pub(super) fn render_month(&self, f: &mut Frame, area: Rect) {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .split(area);
+
+        let mut rows = vec![Constraint::Length(1)];
+        rows.extend([Constraint::Ratio(1, GRID_WEEKS as u32); GRID_WEEKS as usize]);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(rows)
+            .split(panes[0]);
+
+        let day_columns = |area: Rect| {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Ratio(1, 7); 7])
+                .split(area)
+        };
+
+        for (name, cell) in WEEKDAY_NAMES.iter().zip(day_columns(rows[0]).iter()) {
+            f.render_widget(Paragraph::new(*name), *cell);
+        }
+
+        let (start, _) = self.calendar_range();
+        let today = today();
+        for week in 0..GRID_WEEKS as usize {
+            for (weekday, cell) in day_columns(rows[week + 1]).iter().enumerate() {
+                let date = start + Duration::days((week * 7 + weekday) as i64);
+                let border_style = if date == self.calendar_day {
+                    Style::default()
+                        .fg(self.theme.accent())
+                        .add_modifier(Modifier::BOLD)
+                } else if date == today {
+                    Style::default().fg(Color::Yellow)
+                } else if date.month() != self.calendar_day.month() {
+                    Style::default().fg(self.theme.muted())
+                } else {
+                    Style::default()
+                };
+                let lines: Vec<Line> = self
+                    .day_todos(date)
+                    .into_iter()
+                    .map(|todo| {
+                        Line::styled(
+                            format!("{} {}", todo.status.symbol(), todo.title),
+                            todo_style(todo, self.theme),
+                        )
+                    })
+                    .collect();
+                let block = Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border_style)
+                    .title(date.day().to_string());
+                f.render_widget(Paragraph::new(lines).block(block), *cell);
+            }
+        }
+
+        self.render_day(f, panes[1], self.calendar_day, true);
+    }
+
+    pub(super) fn render_week(&self, f: &mut Frame, area: Rect) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Ratio(1, 7); 7])
+            .split(area);
+        let start = week_start(self.calendar_day);
+        for (i, row) in rows.iter().enumerate() {
+            let date = start + Duration::days(i as i64);
+            self.render_day(f, *row, date, date == self.calendar_day);
+        }
+    }
+
+    /// List of todos due on one day, highlighting the cursor when `focused`
+    fn render_day(&self, f: &mut Frame, area: Rect, date: NaiveDate, focused: bool) {
+        let todos = self.day_todos(date);
+        let items: Vec<ListItem> = todos
+            .iter()
+            .map(|todo| {
+                ListItem::new(format!("{} {}", todo.status.symbol(), todo.title))
+                    .style(todo_style(todo, self.theme))
+            })
+            .collect();
+
+        let mut title = format!("{} ({})", date.format("%a %Y-%m-%d"), todos.len());
+        if date == today() {
+            title.push_str(" - today");
+        }
+        let border_style = if focused {
+            Style::default().fg(self.theme.accent())
+        } else {
+            Style::default()
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border_style)
+                    .title(title),
+            )
+            .highlight_style(Style::default().bg(self.theme.highlight()))
+            .highlight_symbol(">> ");
+
+        let mut state = ListState::default();
+        if focused && !todos.is_empty() {
+            state.select(Some(self.calendar_row));
+        }
+        f.render_stateful_widget(list, area, &mut state);
+    }
+}
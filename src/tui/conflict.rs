@@ -0,0 +1,40 @@
+use super::{App, InputMode};
+use crate::Result;
+use crossterm::event::KeyCode;
+
+impl App {
+    /// Ask how to resolve the oldest refused update, unless the user is busy
+    /// typing or confirming something else
+    pub(super) fn prompt_conflict(&mut self) {
+        if !matches!(self.input_mode, InputMode::Normal) {
+            return;
+        }
+        let Some((conflict, _)) = self.conflicts.first() else {
+            return;
+        };
+        self.status_message = format!(
+            "'{}' was changed elsewhere: (r)eload to keep that change, (o)verwrite it with yours",
+            conflict.current.title
+        );
+        self.input_mode = InputMode::Resolving;
+    }
+
+    pub(super) fn handle_conflict_input(&mut self, key: KeyCode) -> Result<bool> {
+        let (conflict, todos) = match key {
+            KeyCode::Char('r') | KeyCode::Char('o') | KeyCode::Esc => self.conflicts.remove(0),
+            _ => return Ok(false),
+        };
+        self.input_mode = InputMode::Normal;
+        if key == KeyCode::Char('o') {
+            let ids = todos.iter().map(|t| t.id.clone()).collect();
+            self.save(ids, move |db| {
+                Box::pin(async move { db.overwrite_many(&todos).await })
+            });
+            self.status_message = format!("Overwrote '{}'", conflict.current.title);
+        } else {
+            // The other change is already loaded
+            self.status_message = format!("Kept the other change to '{}'", conflict.current.title);
+        }
+        Ok(false)
+    }
+}
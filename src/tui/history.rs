@@ -0,0 +1,515 @@
+use crate::database::{TodoDatabase, Transaction};
+use crate::models::Todo;
+use crate::Result;
+use std::collections::HashMap;
+
+/// Maximum number of operations kept on the undo stack
+const DEFAULT_LIMIT: usize = 100;
+
+/// Version a todo is stored at now, by todo ID and the version recorded in an
+/// operation, for versions whose contents undo or redo wrote back
+type Versions = HashMap<(String, i64), i64>;
+
+/// A reversible change made from the TUI
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Create(Todo),
+    /// Move to the trash
+    Delete(Todo),
+    /// Take back out of the trash
+    Restore(Todo),
+    Edit {
+        before: Todo,
+        after: Todo,
+    },
+    SetStatus {
+        before: Todo,
+        after: Todo,
+    },
+    /// Several operations applied together as one bulk action
+    Bulk(Vec<Operation>),
+}
+
+impl Operation {
+    /// The operation that reverts this one
+    pub fn inverse(&self) -> Operation {
+        match self {
+            Operation::Create(todo) => Operation::Delete(todo.clone()),
+            Operation::Delete(todo) => Operation::Restore(todo.clone()),
+            Operation::Restore(todo) => Operation::Delete(todo.clone()),
+            Operation::Edit { before, after } => Operation::Edit {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Operation::SetStatus { before, after } => Operation::SetStatus {
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Operation::Bulk(ops) => Operation::Bulk(ops.iter().rev().map(Self::inverse).collect()),
+        }
+    }
+
+    /// Apply the operation to the database, all or nothing, returning it as
+    /// [`Operation::saved`]
+    ///
+    /// Edits only apply to todos still at `before.version`, so a change made
+    /// elsewhere in the meantime fails with a
+    /// [`Conflict`](crate::database::Conflict) instead of being overwritten.
+    pub async fn apply(&self, db: &TodoDatabase) -> Result<Operation> {
+        db.transaction(|tx| Box::pin(self.apply_in(tx))).await?;
+        Ok(self.saved())
+    }
+
+    /// This operation with each edited todo at the version its write stored,
+    /// one past the version it was written over
+    pub fn saved(&self) -> Operation {
+        match self {
+            Operation::Edit { before, after } => Operation::Edit {
+                before: before.clone(),
+                after: Todo {
+                    version: before.version + 1,
+                    ..after.clone()
+                },
+            },
+            Operation::SetStatus { before, after } => Operation::SetStatus {
+                before: before.clone(),
+                after: Todo {
+                    version: before.version + 1,
+                    ..after.clone()
+                },
+            },
+            Operation::Bulk(ops) => Operation::Bulk(ops.iter().map(Self::saved).collect()),
+            op => op.clone(),
+        }
+    }
+
+    async fn apply_in(&self, tx: &mut Transaction<'_>) -> Result<()> {
+        match self {
+            // Redoing a create whose undo moved the todo to the trash
+            Operation::Create(todo) => match tx.restore_todo(&todo.id).await? {
+                true => Ok(()),
+                false => tx.create_todo(todo).await,
+            },
+            Operation::Delete(todo) => tx.delete_todo(&todo.id).await,
+            Operation::Restore(todo) => tx.restore_todo(&todo.id).await.map(|_| ()),
+            Operation::Edit { before, after } | Operation::SetStatus { before, after } => {
+                let todo = Todo {
+                    version: before.version,
+                    ..after.clone()
+                };
+                tx.update_todo(&todo).await
+            }
+            Operation::Bulk(ops) => {
+                for op in ops {
+                    Box::pin(op.apply_in(tx)).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// This operation with each edit written over the version its recorded
+    /// `before` version is stored at now, per `versions`
+    fn rebased(&self, versions: &Versions) -> Operation {
+        let rebase = |todo: &Todo| Todo {
+            version: versions
+                .get(&(todo.id.clone(), todo.version))
+                .copied()
+                .unwrap_or(todo.version),
+            ..todo.clone()
+        };
+        match self {
+            Operation::Edit { before, after } => Operation::Edit {
+                before: rebase(before),
+                after: after.clone(),
+            }
+            .saved(),
+            Operation::SetStatus { before, after } => Operation::SetStatus {
+                before: rebase(before),
+                after: after.clone(),
+            }
+            .saved(),
+            // Later operations in the bulk may edit the same todos again
+            Operation::Bulk(ops) => {
+                let mut versions = versions.clone();
+                Operation::Bulk(
+                    ops.iter()
+                        .map(|op| {
+                            let rebased = op.rebased(&versions);
+                            op.note_versions(&rebased, &mut versions);
+                            rebased
+                        })
+                        .collect(),
+                )
+            }
+            op => op.clone(),
+        }
+    }
+
+    /// Note in `versions` the version each edit's recorded `after` version is
+    /// stored at now that this operation has been applied as `rebased`
+    fn note_versions(&self, rebased: &Operation, versions: &mut Versions) {
+        match (self, rebased) {
+            (
+                Operation::Edit { after, .. } | Operation::SetStatus { after, .. },
+                Operation::Edit { after: stored, .. } | Operation::SetStatus { after: stored, .. },
+            ) => {
+                versions.insert((after.id.clone(), after.version), stored.version);
+            }
+            (Operation::Bulk(ops), Operation::Bulk(rebased)) => {
+                for (op, rebased) in ops.iter().zip(rebased) {
+                    op.note_versions(rebased, versions);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// IDs of the todos the operation changes
+    pub fn ids(&self) -> Vec<String> {
+        match self {
+            Operation::Create(todo) | Operation::Delete(todo) | Operation::Restore(todo) => {
+                vec![todo.id.clone()]
+            }
+            Operation::Edit { after, .. } | Operation::SetStatus { after, .. } => {
+                vec![after.id.clone()]
+            }
+            Operation::Bulk(ops) => ops.iter().flat_map(Self::ids).collect(),
+        }
+    }
+
+    /// Whether the operation changes any of the todos with the given IDs
+    fn touches(&self, ids: &[String]) -> bool {
+        match self {
+            Operation::Create(todo) | Operation::Delete(todo) | Operation::Restore(todo) => {
+                ids.contains(&todo.id)
+            }
+            Operation::Edit { after, .. } | Operation::SetStatus { after, .. } => {
+                ids.contains(&after.id)
+            }
+            Operation::Bulk(ops) => ops.iter().any(|op| op.touches(ids)),
+        }
+    }
+
+    /// Short human readable description, used in status messages
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Create(todo) => format!("create '{}'", todo.title),
+            Operation::Delete(todo) => format!("delete '{}'", todo.title),
+            Operation::Restore(todo) => format!("restore '{}'", todo.title),
+            Operation::Edit { after, .. } => format!("edit '{}'", after.title),
+            Operation::SetStatus { before, after } => format!(
+                "move '{}' from {} to {}",
+                after.title,
+                before.status.label(),
+                after.status.label()
+            ),
+            Operation::Bulk(ops) => format!("bulk change of {} todos", ops.len()),
+        }
+    }
+}
+
+/// Undo/redo stacks of operations applied in the TUI
+#[derive(Debug)]
+pub struct History {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+    limit: usize,
+    /// Where recorded versions are stored after undoing and redoing, so an
+    /// operation can follow our own writes but not anyone else's
+    versions: Versions,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_LIMIT)
+    }
+}
+
+impl History {
+    /// Create an empty history keeping at most `limit` undoable operations
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit,
+            versions: HashMap::new(),
+        }
+    }
+
+    /// Record an operation that has already been applied, with edits written
+    /// over `before.version`
+    pub fn record(&mut self, op: Operation) {
+        self.undo.push(op.saved());
+        if self.undo.len() > self.limit {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Revert the most recent operation, returning it if there was one
+    ///
+    /// An operation whose todos were changed elsewhere since is dropped, as it
+    /// can no longer be reverted without losing that change.
+    pub async fn undo(&mut self, db: &TodoDatabase) -> Result<Option<Operation>> {
+        let Some((op, write)) = self.take_undo() else {
+            return Ok(None);
+        };
+        self.write(&op, &write, db).await?;
+        Ok(Some(op))
+    }
+
+    /// Re-apply the most recently undone operation, returning it if there was one
+    ///
+    /// Like [`History::undo`], drops the operation if writing it fails.
+    pub async fn redo(&mut self, db: &TodoDatabase) -> Result<Option<Operation>> {
+        let Some((op, write)) = self.take_redo() else {
+            return Ok(None);
+        };
+        self.write(&op, &write, db).await?;
+        Ok(Some(op))
+    }
+
+    /// Move the most recent operation to the redo stack, returning it and the
+    /// operation that reverts it in the database, if there was one
+    ///
+    /// The history assumes the write succeeds, so further undos can be taken
+    /// before it is done; if it fails, [`History::forget`] the operation's todos.
+    pub fn take_undo(&mut self) -> Option<(Operation, Operation)> {
+        let op = self.undo.pop()?;
+        let inverse = op.inverse();
+        let write = inverse.rebased(&self.versions);
+        self.note_versions(&inverse, &write);
+        self.redo.push(op.clone());
+        Some((op, write))
+    }
+
+    /// Move the most recently undone operation back to the undo stack,
+    /// returning it and the operation to write to the database, like
+    /// [`History::take_undo`]
+    pub fn take_redo(&mut self) -> Option<(Operation, Operation)> {
+        let op = self.redo.pop()?;
+        let write = op.rebased(&self.versions);
+        self.note_versions(&op, &write);
+        self.undo.push(op.clone());
+        Some((op, write))
+    }
+
+    /// Write an operation taken off the history, forgetting it if that fails
+    async fn write(&mut self, op: &Operation, write: &Operation, db: &TodoDatabase) -> Result<()> {
+        if let Err(e) = write.apply(db).await {
+            self.forget(&op.ids());
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Remember where `op`, applied as `rebased`, left the versions it recorded
+    fn note_versions(&mut self, op: &Operation, rebased: &Operation) {
+        op.note_versions(rebased, &mut self.versions);
+    }
+
+    /// Drop the operations on the given todos, e.g. when saving them was
+    /// refused, so undoing can't revert somebody else's change
+    pub fn forget(&mut self, ids: &[String]) {
+        self.undo.retain(|op| !op.touches(ids));
+        self.redo.retain(|op| !op.touches(ids));
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Conflict;
+
+    #[tokio::test]
+    async fn test_undo_redo_delete() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todo = Todo::new("Test".to_string(), None);
+        db.create_todo(&todo).await.unwrap();
+
+        let mut history = History::default();
+        let op = Operation::Delete(todo.clone());
+        op.apply(&db).await.unwrap();
+        history.record(op);
+        assert!(db.get_todo(&todo.id).await.unwrap().is_none());
+
+        history.undo(&db).await.unwrap();
+        assert!(db.get_todo(&todo.id).await.unwrap().is_some());
+        assert!(history.can_redo());
+
+        history.redo(&db).await.unwrap();
+        assert!(db.get_todo(&todo.id).await.unwrap().is_none());
+        assert!(!history.can_redo());
+    }
+
+    #[tokio::test]
+    async fn test_redo_create_after_undo() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todo = Todo::new("Test".to_string(), None);
+        let op = Operation::Create(todo.clone());
+        op.apply(&db).await.unwrap();
+
+        let mut history = History::default();
+        history.record(op);
+        history.undo(&db).await.unwrap();
+        assert!(db.get_todo(&todo.id).await.unwrap().is_none());
+        assert_eq!(db.list_trash().await.unwrap().len(), 1);
+
+        history.redo(&db).await.unwrap();
+        assert!(db.get_todo(&todo.id).await.unwrap().is_some());
+        assert!(db.list_trash().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_undo_set_status_and_edit() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let original = Todo::new("Original".to_string(), None);
+        db.create_todo(&original).await.unwrap();
+
+        let mut history = History::default();
+        let mut completed = original.clone();
+        completed.complete();
+        let op = Operation::SetStatus {
+            before: original.clone(),
+            after: completed,
+        };
+        history.record(op.apply(&db).await.unwrap());
+
+        let completed = db.get_todo(&original.id).await.unwrap().unwrap();
+        let mut edited = completed.clone();
+        edited.update(Some("Edited".to_string()), None);
+        let op = Operation::Edit {
+            before: completed,
+            after: edited,
+        };
+        history.record(op.apply(&db).await.unwrap());
+
+        history.undo(&db).await.unwrap();
+        history.undo(&db).await.unwrap();
+        let todo = db.get_todo(&original.id).await.unwrap().unwrap();
+        assert_eq!(todo.title, "Original");
+        assert!(!todo.is_completed());
+        assert!(!history.can_undo());
+
+        history.redo(&db).await.unwrap();
+        history.redo(&db).await.unwrap();
+        let todo = db.get_todo(&original.id).await.unwrap().unwrap();
+        assert_eq!(todo.title, "Edited");
+        assert!(todo.is_completed());
+        assert_eq!(todo.version, 7);
+    }
+
+    #[tokio::test]
+    async fn test_undo_keeps_changes_made_elsewhere() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todo = Todo::new("Original".to_string(), None);
+        db.create_todo(&todo).await.unwrap();
+
+        let mut history = History::default();
+        let mut ours = todo.clone();
+        ours.update(Some("Ours".to_string()), None);
+        let op = Operation::Edit {
+            before: todo.clone(),
+            after: ours,
+        };
+        history.record(op.apply(&db).await.unwrap());
+
+        // Another process renames it after us
+        let mut theirs = db.get_todo(&todo.id).await.unwrap().unwrap();
+        theirs.update(Some("Theirs".to_string()), None);
+        db.update_todo(&theirs).await.unwrap();
+
+        let err = history.undo(&db).await.unwrap_err();
+        assert!(err.is::<Conflict>());
+        let stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+        assert_eq!(stored.title, "Theirs");
+        assert!(!history.can_undo());
+        assert!(!history.can_redo());
+    }
+
+    #[tokio::test]
+    async fn test_undo_stops_at_a_concurrent_edit() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todo = Todo::new("Original".to_string(), None);
+        db.create_todo(&todo).await.unwrap();
+
+        let mut history = History::default();
+        let mut ours = todo.clone();
+        ours.update(Some("Ours".to_string()), None);
+        let op = Operation::Edit {
+            before: todo.clone(),
+            after: ours,
+        };
+        history.record(op.apply(&db).await.unwrap());
+
+        // Another process renames it, then we edit their version
+        let mut theirs = db.get_todo(&todo.id).await.unwrap().unwrap();
+        theirs.update(Some("Theirs".to_string()), None);
+        db.update_todo(&theirs).await.unwrap();
+        let theirs = db.get_todo(&todo.id).await.unwrap().unwrap();
+        let mut again = theirs.clone();
+        again.update(Some("Ours again".to_string()), None);
+        let op = Operation::Edit {
+            before: theirs,
+            after: again,
+        };
+        history.record(op.apply(&db).await.unwrap());
+
+        // Our latest edit is still ours to undo, and redo
+        history.undo(&db).await.unwrap();
+        history.redo(&db).await.unwrap();
+        history.undo(&db).await.unwrap();
+        let stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+        assert_eq!(stored.title, "Theirs");
+
+        // Undoing the first one would throw their rename away
+        let err = history.undo(&db).await.unwrap_err();
+        assert!(err.is::<Conflict>());
+        let stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+        assert_eq!(stored.title, "Theirs");
+        assert!(!history.can_undo());
+    }
+
+    #[tokio::test]
+    async fn test_undo_bulk_delete() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todos: Vec<Todo> = (0..3).map(|i| Todo::new(i.to_string(), None)).collect();
+        for todo in &todos {
+            db.create_todo(todo).await.unwrap();
+        }
+
+        let mut history = History::default();
+        let ids: Vec<String> = todos.iter().map(|t| t.id.clone()).collect();
+        db.delete_many(&ids).await.unwrap();
+        history.record(Operation::Bulk(
+            todos.into_iter().map(Operation::Delete).collect(),
+        ));
+        assert!(db.get_all_todos().await.unwrap().is_empty());
+
+        history.undo(&db).await.unwrap();
+        assert_eq!(db.get_all_todos().await.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_record_clears_redo_and_respects_limit() {
+        let mut history = History::new(2);
+        for i in 0..3 {
+            history.record(Operation::Create(Todo::new(i.to_string(), None)));
+        }
+        assert_eq!(history.undo.len(), 2);
+        history
+            .redo
+            .push(Operation::Create(Todo::new("x".to_string(), None)));
+        history.record(Operation::Create(Todo::new("y".to_string(), None)));
+        assert!(!history.can_redo());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::config::{KeyName, Keymap};
+use crossterm::event::{KeyCode, KeyEvent};
+
+impl Keymap {
+    /// The key event with its code replaced by the bound built-in key, if any
+    pub fn translate(&self, mut key: KeyEvent) -> KeyEvent {
+        if let Some(target) = key_name(key.code).and_then(|name| self.get(name)) {
+            key.code = key_code(target);
+        }
+        key
+    }
+}
+
+/// Name of a key that can be bound, if it is one
+fn key_name(code: KeyCode) -> Option<KeyName> {
+    Some(match code {
+        KeyCode::Char(c) => KeyName::Char(c),
+        KeyCode::Up => KeyName::Up,
+        KeyCode::Down => KeyName::Down,
+        KeyCode::Left => KeyName::Left,
+        KeyCode::Right => KeyName::Right,
+        KeyCode::Enter => KeyName::Enter,
+        KeyCode::Esc => KeyName::Esc,
+        KeyCode::Tab => KeyName::Tab,
+        KeyCode::Backspace => KeyName::Backspace,
+        _ => return None,
+    })
+}
+
+fn key_code(name: KeyName) -> KeyCode {
+    match name {
+        KeyName::Char(c) => KeyCode::Char(c),
+        KeyName::Up => KeyCode::Up,
+        KeyName::Down => KeyCode::Down,
+        KeyName::Left => KeyCode::Left,
+        KeyName::Right => KeyCode::Right,
+        KeyName::Enter => KeyCode::Enter,
+        KeyName::Esc => KeyCode::Esc,
+        KeyName::Tab => KeyCode::Tab,
+        KeyName::Backspace => KeyCode::Backspace,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    #[test]
+    fn test_translate() {
+        let keymap = Keymap::from_pairs([("j", "down"), ("x", "d")]).unwrap();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(
+            keymap.translate(key(KeyCode::Char('j'))).code,
+            KeyCode::Down
+        );
+        assert_eq!(
+            keymap.translate(key(KeyCode::Char('x'))).code,
+            KeyCode::Char('d')
+        );
+        assert_eq!(
+            keymap.translate(key(KeyCode::Char('k'))).code,
+            KeyCode::Char('k')
+        );
+        assert!(Keymap::from_pairs([("j", "sideways")]).is_err());
+    }
+}
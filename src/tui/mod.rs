@@ -1,21 +1,50 @@
-use crate::database::TodoDatabase;
-use crate::models::Todo;
+mod board;
+mod bulk;
+mod calendar;
+mod conflict;
+mod history;
+mod keymap;
+mod query;
+mod saved_views;
+mod stats;
+mod sync;
+mod theme;
+mod trash;
+mod window;
+
+pub use crate::config::{Filter, Keymap, Theme};
+pub use history::{History, Operation};
+
+use crate::database::{Conflict, Placement, Query, Sort, Stats, TodoDatabase};
+use crate::models::{SavedView, Status, Todo, TransitionRules};
 use crate::Result;
+use chrono::{Local, NaiveDate};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyCode, KeyEvent,
+        KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use futures::StreamExt;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
+    text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame, Terminal,
 };
+use std::collections::{HashMap, HashSet};
 use std::io;
+use std::time::Instant;
+use tokio::sync::mpsc;
 use tokio::time::Duration;
 
+/// Maximum delay between two clicks on the same row to count as a double-click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
 /// Application state
 pub struct App {
     db: TodoDatabase,
@@ -25,25 +54,116 @@ pub struct App {
     input_mode: InputMode,
     status_message: String,
     filter: Filter,
+    /// Query entered in the filter bar, applied on top of `filter`
+    query: Option<Query>,
+    /// Index of the first loaded todo in the whole list, see [`window`]
+    list_offset: usize,
+    /// Number of todos in the whole list
+    list_total: usize,
+    /// Saved views, shown as header tabs after the built-in filters
+    views: Vec<SavedView>,
+    /// Name of the saved view `query` came from
+    active_view: Option<String>,
+    sort: Sort,
+    history: History,
+    /// ID of the todo being edited, `None` when entering a new todo
+    editing_id: Option<String>,
+    confirm_destructive: bool,
+    pending_delete: Vec<Todo>,
+    /// IDs of todos marked for a bulk action
+    marked: HashSet<String>,
+    /// Index the last mark was toggled at, used as the start of range marks
+    mark_anchor: Option<usize>,
+    /// Screen areas from the last draw, used to hit-test mouse clicks
+    list_area: Rect,
+    tab_areas: Vec<(Rect, Tab)>,
+    last_click: Option<(usize, Instant)>,
+    view: View,
+    /// Focused board column, an index into `Status::BOARD`
+    board_column: usize,
+    /// Cursor row within each board column
+    board_rows: [usize; Status::BOARD.len()],
+    wip_limits: HashMap<Status, usize>,
+    transition_rules: TransitionRules,
+    /// Day under the cursor in the calendar views
+    calendar_day: NaiveDate,
+    /// Cursor row within the todos due on `calendar_day`
+    calendar_row: usize,
+    stats: Option<Stats>,
+    theme: Theme,
+    keymap: Keymap,
+    /// Queue of the background database worker, started on first use
+    jobs: Option<mpsc::UnboundedSender<sync::Job>>,
+    updates_tx: mpsc::UnboundedSender<sync::Update>,
+    updates_rx: mpsc::UnboundedReceiver<sync::Update>,
+    /// Number of queued jobs whose results haven't been applied yet
+    pending: usize,
+    /// Bumped on every local change or reload, to discard outdated loads
+    generation: u64,
+    /// Refused updates waiting to be reloaded or overwritten, with the todos
+    /// we tried to write
+    conflicts: Vec<(Conflict, Vec<Todo>)>,
+}
+
+/// Layout of the main area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    List,
+    Board,
+    /// Month grid of todos by due date
+    Month,
+    /// Week agenda of todos by due date
+    Week,
+    /// Statistics and productivity report
+    Stats,
+    /// Deleted todos that can be restored
+    Trash,
 }
 
 #[derive(Debug, Clone)]
 pub enum InputMode {
     Normal,
     Editing,
+    Confirming,
+    /// Typing a query into the filter bar
+    Filtering,
+    /// Choosing between another change and ours after a conflicting update
+    Resolving,
+    /// Typing the value a bulk action sets on the targeted todos
+    Assigning(bulk::Field),
 }
 
-#[derive(Debug, Clone)]
-pub enum Filter {
-    All,
-    Completed,
-    Pending,
+/// A header tab: a built-in filter or a saved view
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Tab {
+    Filter(Filter),
+    View(String),
+}
+
+impl Filter {
+    /// Filters in the order they appear as header tabs
+    const TABS: [Filter; 4] = [
+        Filter::All,
+        Filter::Pending,
+        Filter::Completed,
+        Filter::Archived,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Filter::All => "All",
+            Filter::Completed => "Completed",
+            Filter::Pending => "Pending",
+            Filter::Archived => "Archived",
+        }
+    }
 }
 
 impl App {
     pub fn new(db: TodoDatabase) -> Self {
         let mut selected = ListState::default();
         selected.select(Some(0));
+        let (updates_tx, updates_rx) = mpsc::unbounded_channel();
 
         Self {
             db,
@@ -53,9 +173,92 @@ impl App {
             input_mode: InputMode::Normal,
             status_message: "Welcome to Todo App! Press 'h' for help.".to_string(),
             filter: Filter::All,
+            query: None,
+            list_offset: 0,
+            list_total: 0,
+            views: Vec::new(),
+            active_view: None,
+            sort: Sort::default(),
+            history: History::default(),
+            editing_id: None,
+            confirm_destructive: false,
+            pending_delete: Vec::new(),
+            marked: HashSet::new(),
+            mark_anchor: None,
+            list_area: Rect::default(),
+            tab_areas: Vec::new(),
+            last_click: None,
+            view: View::List,
+            board_column: 0,
+            board_rows: [0; Status::BOARD.len()],
+            wip_limits: HashMap::new(),
+            transition_rules: TransitionRules::permissive(),
+            calendar_day: Local::now().date_naive(),
+            calendar_row: 0,
+            stats: None,
+            theme: Theme::default(),
+            keymap: Keymap::default(),
+            jobs: None,
+            updates_tx,
+            updates_rx,
+            pending: 0,
+            generation: 0,
+            conflicts: Vec::new(),
         }
     }
 
+    /// Restrict which status changes are allowed
+    pub fn with_transition_rules(mut self, rules: TransitionRules) -> Self {
+        self.transition_rules = rules;
+        self
+    }
+
+    /// Start in the given view
+    pub fn with_view(mut self, view: View) -> Self {
+        self.view = view;
+        self
+    }
+
+    /// Limit the number of todos in a board column; moves into a full column are refused
+    pub fn with_wip_limit(mut self, status: Status, limit: usize) -> Self {
+        self.wip_limits.insert(status, limit);
+        self
+    }
+
+    /// Start with the given sort order instead of newest first
+    pub fn with_sort(mut self, sort: Sort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Start with the given filter instead of all todos
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_query(mut self, query: Query) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Add key bindings on top of the built-in keys
+    pub fn with_keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// Ask for confirmation before destructive actions such as delete
+    pub fn with_confirmation(mut self, confirm: bool) -> Self {
+        self.confirm_destructive = confirm;
+        self
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         // Setup terminal
         enable_raw_mode()?;
@@ -78,132 +281,360 @@ impl App {
         result
     }
 
+    /// Redraw at least this often, so dates such as "overdue" stay current
+    const TICK: Duration = Duration::from_secs(1);
+    /// How often to check the database for changes made by other processes
+    const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
     async fn run_app<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        self.views = self.db.list_views().await?;
         self.refresh_todos().await?;
 
-        loop {
+        let mut events = EventStream::new();
+        let mut ticks = tokio::time::interval(Self::TICK);
+        let (changes_tx, mut changes) = mpsc::channel(1);
+        let mut watch = Box::pin(self.db.changes(Self::WATCH_INTERVAL).await?);
+        let watcher = tokio::spawn(async move {
+            while let Some(change) = watch.next().await {
+                let failed = change.is_err();
+                if changes_tx.send(change).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let result = loop {
+            self.prompt_conflict();
             terminal.draw(|f| self.ui(f))?;
 
-            if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(key) = event::read()? {
-                    if key.kind == KeyEventKind::Press {
-                        match self.input_mode {
-                            InputMode::Normal => {
-                                if self.handle_normal_input(key.code).await? {
-                                    break;
-                                }
-                            }
-                            InputMode::Editing => {
-                                if self.handle_editing_input(key.code).await? {
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(Ok(event)) => match self.handle_event(event).await {
+                        Ok(false) => {}
+                        Ok(true) => break Ok(()),
+                        Err(e) => break Err(e),
+                    },
+                    Some(Err(e)) => break Err(e.into()),
+                    None => break Ok(()),
+                },
+                Some(update) = self.updates_rx.recv() => self.apply_update(update),
+                Some(change) = changes.recv() => match change {
+                    // Our own writes are reported too; their reload is already queued
+                    Ok(()) if self.pending == 0 => self.reload(),
+                    Ok(()) => {}
+                    Err(e) => self.status_message = format!("Stopped watching for changes: {}", e),
+                },
+                _ = ticks.tick() => {}
             }
-        }
+        };
 
-        Ok(())
+        watcher.abort();
+        // Finish the queued writes before the terminal is restored
+        self.settle().await;
+        result
     }
 
-    async fn handle_normal_input(&mut self, key: KeyCode) -> Result<bool> {
-        match key {
+    /// Handle a terminal event, returning whether to quit
+    async fn handle_event(&mut self, event: Event) -> Result<bool> {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match self.input_mode {
+                InputMode::Normal => self.handle_normal_input(self.keymap.translate(key)).await,
+                InputMode::Editing => self.handle_editing_input(key.code).await,
+                InputMode::Confirming => self.handle_confirming_input(key.code).await,
+                InputMode::Filtering => self.handle_query_input(key.code).await,
+                InputMode::Resolving => self.handle_conflict_input(key.code),
+                InputMode::Assigning(field) => self.handle_assign_input(field, key.code).await,
+            },
+            Event::Mouse(mouse) if matches!(self.input_mode, InputMode::Normal) => {
+                self.handle_mouse(mouse).await?;
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn handle_normal_input(&mut self, key: KeyEvent) -> Result<bool> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            if key.code == KeyCode::Char('r') {
+                self.redo();
+            }
+            return Ok(false);
+        }
+
+        let consumed = match self.view {
+            View::Board => self.handle_board_input(key.code).await?,
+            View::Month | View::Week => self.handle_calendar_input(key.code).await?,
+            View::Trash => self.handle_trash_input(key.code).await?,
+            View::List | View::Stats => false,
+        };
+        if consumed {
+            return Ok(false);
+        }
+
+        match key.code {
             KeyCode::Char('q') => return Ok(true),
-            KeyCode::Char('h') => {
-                self.status_message = "Commands: q=quit, n=new todo, d=delete, c=toggle complete, a=all, p=pending, f=finished, ↑↓=navigate".to_string();
+            KeyCode::Char('?') if self.view == View::Board => {
+                self.status_message = "Board: ←→=focus column, ↑↓=navigate, h/l=move card left/right, b=list view, n=new, e=edit, d=delete, c=toggle complete, u=undo, Ctrl-r=redo, q=quit".to_string();
+            }
+            KeyCode::Char('?') if matches!(self.view, View::Month | View::Week) => {
+                self.status_message = "Calendar: ←→=day, ↑↓=week, j/k=select todo, t=today, +/-=reschedule ±1 day, N=next Monday, m=month, w=week, q=quit".to_string();
+            }
+            KeyCode::Char('h') | KeyCode::Char('?') => {
+                self.status_message = "Commands: q=quit, b=board view, m=month, w=week agenda, S=stats, T=trash, n=new todo, e=edit, d=delete, c=toggle complete, o=reopen, i=in progress, B=blocked, W=waiting, X=cancel, #=tag, M=move to project, P=priority, space=mark, V=mark range, *=mark all, u=undo, Ctrl-r=redo, s=cycle sort, J/K=move down/up, a=all, p=pending, f=finished, A=archived, /=filter query, v=next saved view, ↑↓=navigate".to_string();
             }
             KeyCode::Char('n') => {
                 self.input_mode = InputMode::Editing;
                 self.input.clear();
+                self.editing_id = None;
                 self.status_message = "Enter new todo (ESC to cancel, Enter to save):".to_string();
             }
+            KeyCode::Char('e') => {
+                if let Some(todo) = self.selected_todo().cloned() {
+                    self.input_mode = InputMode::Editing;
+                    self.input = todo.title;
+                    self.editing_id = Some(todo.id);
+                    self.status_message = "Edit todo (ESC to cancel, Enter to save):".to_string();
+                }
+            }
             KeyCode::Char('d') => {
-                if let Some(index) = self.selected.selected() {
-                    if index < self.todos.len() {
-                        let todo = &self.todos[index];
-                        self.db.delete_todo(&todo.id).await?;
-                        self.refresh_todos().await?;
-                        self.status_message = "Todo deleted!".to_string();
-                    }
+                let todos = self.targets();
+                if todos.is_empty() {
+                    return Ok(false);
+                }
+                if self.confirm_destructive {
+                    self.status_message = match todos.as_slice() {
+                        [todo] => format!("Delete '{}'? (y/n)", todo.title),
+                        _ => format!("Delete {} todos? (y/n)", todos.len()),
+                    };
+                    self.pending_delete = todos;
+                    self.input_mode = InputMode::Confirming;
+                } else {
+                    self.delete(todos).await?;
                 }
             }
             KeyCode::Char('c') => {
+                if !self.marked.is_empty() {
+                    self.set_status(self.targets(), Status::Done).await?;
+                } else if let Some(todo) = self.selected_todo().cloned() {
+                    self.toggle_completed(todo).await?;
+                }
+            }
+            KeyCode::Char('o') => self.set_status(self.targets(), Status::Todo).await?,
+            KeyCode::Char('i') => self.set_status(self.targets(), Status::InProgress).await?,
+            KeyCode::Char('B') => self.set_status(self.targets(), Status::Blocked).await?,
+            KeyCode::Char('W') => self.set_status(self.targets(), Status::Waiting).await?,
+            KeyCode::Char('X') => self.set_status(self.targets(), Status::Cancelled).await?,
+            KeyCode::Char('#') => self.start_assign(bulk::Field::Tags),
+            KeyCode::Char('M') => self.start_assign(bulk::Field::Project),
+            KeyCode::Char('P') => self.start_assign(bulk::Field::Priority),
+            KeyCode::Char(' ') => {
                 if let Some(index) = self.selected.selected() {
-                    if index < self.todos.len() {
-                        let mut todo = self.todos[index].clone();
-                        if todo.completed {
-                            todo.uncomplete();
-                        } else {
-                            todo.complete();
+                    if let Some(todo) = self.todos.get(index) {
+                        if !self.marked.remove(&todo.id) {
+                            self.marked.insert(todo.id.clone());
                         }
-                        self.db.update_todo(&todo).await?;
-                        self.refresh_todos().await?;
-                        self.status_message = if todo.completed {
-                            "Todo marked as completed!".to_string()
-                        } else {
-                            "Todo marked as pending!".to_string()
-                        };
+                        self.mark_anchor = Some(index);
+                        self.status_message = format!("{} marked", self.marked.len());
                     }
                 }
             }
-            KeyCode::Char('a') => {
-                self.filter = Filter::All;
-                self.refresh_todos().await?;
-                self.status_message = "Showing all todos".to_string();
-            }
-            KeyCode::Char('p') => {
-                self.filter = Filter::Pending;
-                self.refresh_todos().await?;
-                self.status_message = "Showing pending todos".to_string();
-            }
-            KeyCode::Char('f') => {
-                self.filter = Filter::Completed;
-                self.refresh_todos().await?;
-                self.status_message = "Showing completed todos".to_string();
-            }
-            KeyCode::Down => {
-                let i = match self.selected.selected() {
-                    Some(i) => {
-                        if i >= self.todos.len().saturating_sub(1) {
-                            0
-                        } else {
-                            i + 1
-                        }
+            KeyCode::Char('V') => {
+                if let Some(index) = self.selected.selected() {
+                    let anchor = self.mark_anchor.unwrap_or(index);
+                    let (from, to) = (anchor.min(index), anchor.max(index));
+                    for todo in self.todos.iter().take(to + 1).skip(from) {
+                        self.marked.insert(todo.id.clone());
                     }
-                    None => 0,
+                    self.mark_anchor = Some(index);
+                    self.status_message = format!("{} marked", self.marked.len());
+                }
+            }
+            KeyCode::Char('*') => {
+                if self.marked.len() == self.todos.len() {
+                    self.marked.clear();
+                } else {
+                    self.marked = self.todos.iter().map(|t| t.id.clone()).collect();
+                }
+                self.status_message = format!("{} marked", self.marked.len());
+            }
+            KeyCode::Esc => {
+                self.marked.clear();
+                self.mark_anchor = None;
+                self.status_message = "Marks cleared".to_string();
+            }
+            KeyCode::Char('u') => {
+                self.undo();
+            }
+            KeyCode::Char('s') => {
+                let presets = Sort::presets();
+                let next = presets
+                    .iter()
+                    .position(|p| *p == self.sort)
+                    .map_or(0, |i| (i + 1) % presets.len());
+                self.sort = presets[next].clone();
+                self.list_offset = 0;
+                self.reload();
+                self.status_message = format!("Sorted by {}", self.sort);
+            }
+            KeyCode::Char('b') => self.toggle_view(View::Board).await?,
+            KeyCode::Char('m') => self.toggle_view(View::Month).await?,
+            KeyCode::Char('w') => self.toggle_view(View::Week).await?,
+            KeyCode::Char('S') => self.toggle_view(View::Stats).await?,
+            KeyCode::Char('T') => self.toggle_view(View::Trash).await?,
+            KeyCode::Char('J') => self.move_selected(1),
+            KeyCode::Char('K') => self.move_selected(-1),
+            KeyCode::Char('a') => self.set_filter(Filter::All).await?,
+            KeyCode::Char('p') => self.set_filter(Filter::Pending).await?,
+            KeyCode::Char('f') => self.set_filter(Filter::Completed).await?,
+            KeyCode::Char('A') => self.set_filter(Filter::Archived).await?,
+            KeyCode::Char('/') => self.start_query(),
+            KeyCode::Char('v') => self.cycle_view().await?,
+            KeyCode::Down => self.step(1).await?,
+            KeyCode::Up => self.step(-1).await?,
+            KeyCode::PageDown => self.scroll(self.page_height()).await?,
+            KeyCode::PageUp => self.scroll(-self.page_height()).await?,
+            KeyCode::Home => self.select_index(0).await?,
+            KeyCode::End => self.select_index(self.list_total.saturating_sub(1)).await?,
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Switch to `view`, or back to the list if it is already shown
+    async fn toggle_view(&mut self, view: View) -> Result<()> {
+        self.view = if self.view == view { View::List } else { view };
+        self.reload();
+        self.status_message = match self.view {
+            View::List => "List view".to_string(),
+            View::Board => "Board view (press '?' for help)".to_string(),
+            View::Month => "Month view (press '?' for help)".to_string(),
+            View::Week => "Week agenda (press '?' for help)".to_string(),
+            View::Stats => "Statistics (press 'S' to go back)".to_string(),
+            View::Trash => "Trash (press '?' for help)".to_string(),
+        };
+        Ok(())
+    }
+
+    async fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        let delta = match mouse.kind {
+            MouseEventKind::ScrollDown => 1,
+            MouseEventKind::ScrollUp => -1,
+            _ => 0,
+        };
+        match self.view {
+            View::List | View::Trash => {}
+            View::Stats => return Ok(()),
+            View::Board => {
+                self.scroll_column(delta);
+                return Ok(());
+            }
+            View::Month | View::Week => {
+                self.scroll_day(delta);
+                return Ok(());
+            }
+        }
+
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let hit = |area: &Rect| {
+                    mouse.column >= area.x
+                        && mouse.column < area.x + area.width
+                        && mouse.row >= area.y
+                        && mouse.row < area.y + area.height
                 };
-                self.selected.select(Some(i));
-            }
-            KeyCode::Up => {
-                let i = match self.selected.selected() {
-                    Some(i) => {
-                        if i == 0 {
-                            self.todos.len().saturating_sub(1)
-                        } else {
-                            i - 1
-                        }
+                match self.tab_areas.iter().find(|(area, _)| hit(area)) {
+                    Some((_, Tab::Filter(filter))) => return self.set_filter(*filter).await,
+                    Some((_, Tab::View(name))) => {
+                        let name = name.clone();
+                        return self.select_view(&name).await;
                     }
-                    None => 0,
+                    None => {}
+                }
+
+                // Rows inside the list block's borders map to visible todos
+                let list = self.list_area;
+                if list.height < 2
+                    || mouse.row <= list.y
+                    || mouse.row >= list.y + list.height - 1
+                    || !hit(&list)
+                {
+                    return Ok(());
+                }
+                let index = self.selected.offset() + (mouse.row - list.y - 1) as usize;
+                let Some(todo) = self.todos.get(index).cloned() else {
+                    return Ok(());
                 };
-                self.selected.select(Some(i));
+                self.selected.select(Some(index));
+
+                let now = Instant::now();
+                let double = matches!(self.last_click, Some((last, at)) if last == index && now.duration_since(at) <= DOUBLE_CLICK);
+                if double && self.view == View::List {
+                    self.last_click = None;
+                    self.toggle_completed(todo).await?;
+                } else {
+                    self.last_click = Some((index, now));
+                }
             }
+            MouseEventKind::ScrollDown => self.scroll(1).await?,
+            MouseEventKind::ScrollUp => self.scroll(-1).await?,
             _ => {}
         }
-        Ok(false)
+        Ok(())
+    }
+
+    /// Rows of todos visible in the list, for page-wise scrolling
+    fn page_height(&self) -> isize {
+        (self.list_area.height.saturating_sub(2) as isize).max(1)
+    }
+
+    async fn set_filter(&mut self, filter: Filter) -> Result<()> {
+        // A saved view replaces the filter, so leaving it drops its query too
+        if self.active_view.take().is_some() {
+            self.query = None;
+        }
+        self.filter = filter;
+        self.list_offset = 0;
+        self.reload();
+        self.status_message = match filter {
+            Filter::All => "Showing all todos".to_string(),
+            Filter::Completed => "Showing completed todos".to_string(),
+            Filter::Pending => "Showing pending todos".to_string(),
+            Filter::Archived => "Showing archived todos".to_string(),
+        };
+        Ok(())
     }
 
     async fn handle_editing_input(&mut self, key: KeyCode) -> Result<bool> {
         match key {
-            KeyCode::Enter => {
-                if !self.input.is_empty() {
-                    let todo = Todo::new(self.input.trim().to_string(), None);
-                    self.db.create_todo(&todo).await?;
-                    self.input.clear();
-                    self.input_mode = InputMode::Normal;
-                    self.refresh_todos().await?;
-                    self.status_message = "Todo added!".to_string();
+            KeyCode::Enter if !self.input.trim().is_empty() => {
+                let title = self.input.trim().to_string();
+                match self.editing_id.take() {
+                    Some(id) => {
+                        let before = self.todos.iter().find(|t| t.id == id).cloned();
+                        if let Some(before) = before {
+                            let mut todo = before.clone();
+                            todo.update(Some(title), None);
+                            self.save_todos(vec![todo.clone()]);
+                            self.history.record(Operation::Edit {
+                                before,
+                                after: todo,
+                            });
+                            self.status_message = "Todo updated!".to_string();
+                        }
+                    }
+                    None => {
+                        let todo = Todo::new(title, None);
+                        self.insert_local(todo.clone());
+                        let saved = todo.clone();
+                        self.save(vec![todo.id.clone()], move |db| {
+                            Box::pin(async move { db.create_todo(&saved).await })
+                        });
+                        self.history.record(Operation::Create(todo));
+                        self.status_message = "Todo added!".to_string();
+                    }
                 }
+                self.input.clear();
+                self.input_mode = InputMode::Normal;
             }
             KeyCode::Char(c) => {
                 self.input.push(c);
@@ -213,6 +644,7 @@ impl App {
             }
             KeyCode::Esc => {
                 self.input.clear();
+                self.editing_id = None;
                 self.input_mode = InputMode::Normal;
                 self.status_message = "Cancelled".to_string();
             }
@@ -221,24 +653,212 @@ impl App {
         Ok(false)
     }
 
-    async fn refresh_todos(&mut self) -> Result<()> {
-        self.todos = match self.filter {
-            Filter::All => self.db.get_all_todos().await?,
-            Filter::Completed => self.db.get_todos_by_status(true).await?,
-            Filter::Pending => self.db.get_todos_by_status(false).await?,
+    async fn handle_confirming_input(&mut self, key: KeyCode) -> Result<bool> {
+        self.input_mode = InputMode::Normal;
+        let todos = std::mem::take(&mut self.pending_delete);
+        match key {
+            KeyCode::Char('y') | KeyCode::Char('Y') if !todos.is_empty() => {
+                if self.view == View::Trash {
+                    self.purge(todos)
+                } else {
+                    self.delete(todos).await?
+                }
+            }
+            _ => self.status_message = "Cancelled".to_string(),
+        }
+        Ok(false)
+    }
+
+    fn selected_todo(&self) -> Option<&Todo> {
+        match self.view {
+            View::List | View::Trash => self.selected.selected().and_then(|i| self.todos.get(i)),
+            View::Board => self.selected_card(),
+            View::Month | View::Week => self.selected_day_todo(),
+            View::Stats => None,
+        }
+    }
+
+    /// Todos a bulk action applies to: the marked ones, or else the selected one
+    fn targets(&self) -> Vec<Todo> {
+        if self.marked.is_empty() {
+            self.selected_todo().cloned().into_iter().collect()
+        } else {
+            self.todos
+                .iter()
+                .filter(|t| self.marked.contains(&t.id))
+                .cloned()
+                .collect()
+        }
+    }
+
+    async fn delete(&mut self, todos: Vec<Todo>) -> Result<()> {
+        let count = todos.len();
+        let ids: Vec<String> = todos.iter().map(|t| t.id.clone()).collect();
+        self.remove_local(&ids);
+        let op = if let [todo] = todos.as_slice() {
+            let id = todo.id.clone();
+            self.save(ids, move |db| {
+                Box::pin(async move { db.delete_todo(&id).await })
+            });
+            Operation::Delete(todo.clone())
+        } else {
+            let deleted = ids.clone();
+            self.save(ids, move |db| {
+                Box::pin(async move { db.delete_many(&deleted).await })
+            });
+            Operation::Bulk(todos.into_iter().map(Operation::Delete).collect())
         };
+        self.history.record(op);
+        self.marked.clear();
+        self.status_message = if count == 1 {
+            "Todo moved to trash! Press 'u' to undo, 'T' to view trash.".to_string()
+        } else {
+            format!(
+                "{} todos moved to trash! Press 'u' to undo, 'T' to view trash.",
+                count
+            )
+        };
+        Ok(())
+    }
+
+    async fn toggle_completed(&mut self, todo: Todo) -> Result<()> {
+        let status = if todo.is_completed() {
+            Status::Todo
+        } else {
+            Status::Done
+        };
+        self.set_status(vec![todo], status).await
+    }
+
+    /// Move todos to `status`, skipping those the transition rules don't allow
+    async fn set_status(&mut self, todos: Vec<Todo>, status: Status) -> Result<()> {
+        let mut ops = Vec::new();
+        let mut updated = Vec::new();
+        let mut refused = 0;
+        for before in todos.into_iter().filter(|t| t.status != status) {
+            let mut after = before.clone();
+            if after
+                .transition(status, None, &self.transition_rules)
+                .is_err()
+            {
+                refused += 1;
+                continue;
+            }
+            updated.push(after.clone());
+            ops.push(Operation::SetStatus { before, after });
+        }
 
-        // Adjust selection if needed
-        if self.todos.is_empty() {
-            self.selected.select(None);
-        } else if let Some(selected) = self.selected.selected() {
-            if selected >= self.todos.len() {
-                self.selected.select(Some(self.todos.len() - 1));
+        let count = ops.len();
+        if count == 0 {
+            if refused > 0 {
+                self.status_message = format!("Status change to {} is not allowed", status.label());
             }
+            return Ok(());
+        }
+        let op = if count == 1 {
+            ops.remove(0)
         } else {
-            self.selected.select(Some(0));
+            Operation::Bulk(ops)
+        };
+
+        self.save_todos(updated);
+        self.history.record(op);
+        self.marked.clear();
+
+        self.status_message = if count == 1 {
+            format!("Todo marked as {}!", status.label())
+        } else {
+            format!("{} todos marked as {}!", count, status.label())
+        };
+        if refused > 0 {
+            self.status_message
+                .push_str(&format!(" ({} not allowed)", refused));
+        }
+        Ok(())
+    }
+
+    /// Move the selected todo one place down (`offset = 1`) or up (`offset = -1`)
+    /// in manual order, switching to manual order first if needed
+    fn move_selected(&mut self, offset: isize) {
+        if self.sort != Sort::manual() {
+            self.sort = Sort::manual();
+            self.reload();
+            self.status_message = "Switched to manual order".to_string();
+            return;
+        }
+        let Some(index) = self.selected.selected() else {
+            return;
+        };
+        let Some(target) = index
+            .checked_add_signed(offset)
+            .filter(|&i| i < self.todos.len())
+        else {
+            return;
+        };
+
+        let id = self.todos[index].id.clone();
+        let neighbour = self.todos[target].id.clone();
+        self.todos.swap(index, target);
+        self.selected.select(Some(target));
+        // Positions aren't part of the undo history
+        self.save(Vec::new(), move |db| {
+            Box::pin(async move {
+                let placement = if offset > 0 {
+                    Placement::After(&neighbour)
+                } else {
+                    Placement::Before(&neighbour)
+                };
+                db.move_todo(&id, placement).await
+            })
+        });
+        self.status_message = "Todo moved".to_string();
+    }
+
+    fn undo(&mut self) {
+        self.status_message = match self.history.take_undo() {
+            Some((op, write)) => {
+                self.save_history(&op, write, "undo");
+                format!("Undid {}", op.describe())
+            }
+            None => "Nothing to undo".to_string(),
+        };
+    }
+
+    fn redo(&mut self) {
+        self.status_message = match self.history.take_redo() {
+            Some((op, write)) => {
+                self.save_history(&op, write, "redo");
+                format!("Redid {}", op.describe())
+            }
+            None => "Nothing to redo".to_string(),
+        };
+    }
+
+    /// The filter bar query narrowed to `statuses`, if either applies
+    fn list_query(&self, statuses: &[Status]) -> Option<Query> {
+        match self.query.clone() {
+            Some(query) if !statuses.is_empty() => Some(Query::status(statuses).and(query)),
+            None if !statuses.is_empty() => Some(Query::status(statuses)),
+            query => query,
         }
+    }
+
+    /// Statuses the current view is limited to, if any
+    fn view_statuses(&self) -> &'static [Status] {
+        // The board shows every status as its own column, so it ignores the filter
+        match (self.view, self.filter) {
+            (View::Board, _) => &Status::BOARD,
+            (_, Filter::All | Filter::Archived) => &[],
+            (_, Filter::Completed) => &[Status::Done],
+            (_, Filter::Pending) => &Status::OPEN,
+        }
+    }
 
+    /// Reload the current view, waiting for it and any queued writes
+    async fn refresh_todos(&mut self) -> Result<()> {
+        self.settle().await;
+        let snapshot = self.load_request().run(&self.db).await?;
+        self.apply_snapshot(snapshot);
         Ok(())
     }
 
@@ -253,63 +873,188 @@ impl App {
             ])
             .split(f.size());
 
-        // Title
-        let title = Paragraph::new("📝 Todo App")
-            .style(Style::default().fg(Color::Cyan))
-            .alignment(Alignment::Center)
-            .block(Block::default().borders(Borders::ALL));
-        f.render_widget(title, chunks[0]);
+        // Title with clickable filter tabs
+        let header = Block::default()
+            .borders(Borders::ALL)
+            .title("📝 Todo App")
+            .title_alignment(Alignment::Center)
+            .style(Style::default().fg(self.theme.accent()));
+        let inner = header.inner(chunks[0]);
+        let mut spans = Vec::new();
+        let mut x = inner.x;
+        self.tab_areas.clear();
+        let tabs: Vec<Tab> = match self.view {
+            View::List => Filter::TABS
+                .into_iter()
+                .map(Tab::Filter)
+                .chain(self.views.iter().map(|v| Tab::View(v.name.clone())))
+                .collect(),
+            View::Board | View::Month | View::Week | View::Stats | View::Trash => Vec::new(),
+        };
+        match self.view {
+            View::List => {}
+            View::Board => spans.push(Span::raw(" Board ")),
+            View::Stats => spans.push(Span::raw(" Statistics ")),
+            View::Trash => spans.push(Span::raw(" Trash ")),
+            View::Month | View::Week => spans.push(Span::raw(self.calendar_title())),
+        }
+        for (i, tab) in tabs.into_iter().enumerate() {
+            if i > 0 {
+                let divider = Span::raw(" | ");
+                x += divider.width() as u16;
+                spans.push(divider);
+            }
+            let (label, active) = match &tab {
+                Tab::Filter(filter) => (
+                    filter.label().to_string(),
+                    self.active_view.is_none() && *filter == self.filter,
+                ),
+                Tab::View(name) => (
+                    format!("@{}", name),
+                    self.active_view.as_ref() == Some(name),
+                ),
+            };
+            let style = if active {
+                Style::default()
+                    .fg(self.theme.on_accent())
+                    .bg(self.theme.accent())
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let span = Span::styled(format!(" {} ", label), style);
+            let width = span.width() as u16;
+            self.tab_areas
+                .push((Rect::new(x, inner.y, width, inner.height.min(1)), tab));
+            x += width;
+            spans.push(span);
+        }
+        f.render_widget(Paragraph::new(Line::from(spans)).block(header), chunks[0]);
+
+        match self.view {
+            View::List | View::Trash => self.render_list(f, chunks[1]),
+            View::Board => self.render_board(f, chunks[1]),
+            View::Month => self.render_month(f, chunks[1]),
+            View::Week => self.render_week(f, chunks[1]),
+            View::Stats => self.render_stats(f, chunks[1]),
+        }
+
+        // Status/Input bar
+        let status_text = match self.input_mode {
+            InputMode::Normal | InputMode::Confirming | InputMode::Resolving => {
+                self.status_message.clone()
+            }
+            InputMode::Editing if self.editing_id.is_some() => {
+                format!("Edit todo: {}", self.input)
+            }
+            InputMode::Editing => format!("New todo: {}", self.input),
+            InputMode::Filtering => format!("Filter: {}", self.input),
+            InputMode::Assigning(field) => format!("{}: {}", field.label(), self.input),
+        };
+        // The filter bar keeps hints and parse errors visible above the input
+        let status_title = match self.input_mode {
+            InputMode::Filtering | InputMode::Assigning(_) => self.status_message.clone(),
+            _ => "Status".to_string(),
+        };
+
+        let status = Paragraph::new(status_text)
+            .style(match self.input_mode {
+                InputMode::Normal => Style::default(),
+                InputMode::Editing | InputMode::Filtering | InputMode::Assigning(_) => {
+                    Style::default().fg(Color::Yellow)
+                }
+                InputMode::Confirming | InputMode::Resolving => Style::default().fg(Color::Red),
+            })
+            .wrap(Wrap { trim: true })
+            .block(Block::default().borders(Borders::ALL).title(status_title));
+
+        f.render_widget(status, chunks[2]);
+    }
 
-        // Todo list
+    fn render_list(&mut self, f: &mut Frame, area: Rect) {
         let todos: Vec<ListItem> = self
             .todos
             .iter()
             .map(|todo| {
-                let status = if todo.completed { "✓" } else { "○" };
-                let style = if todo.completed {
-                    Style::default()
-                        .fg(Color::Green)
-                        .add_modifier(Modifier::CROSSED_OUT)
+                let status = todo.status.symbol();
+                let mark = if self.marked.contains(&todo.id) {
+                    "* "
                 } else {
-                    Style::default().fg(Color::White)
+                    ""
+                };
+                let style = match todo.status {
+                    Status::Done => Style::default()
+                        .fg(Color::Green)
+                        .add_modifier(Modifier::CROSSED_OUT),
+                    Status::Cancelled => Style::default()
+                        .fg(self.theme.muted())
+                        .add_modifier(Modifier::CROSSED_OUT),
+                    Status::InProgress => Style::default().fg(Color::Yellow),
+                    Status::Blocked | Status::Waiting => Style::default().fg(Color::Red),
+                    Status::Todo => Style::default().fg(self.theme.text()),
                 };
 
-                let content = format!("{} {}", status, todo.title);
+                let mut content = format!("{}{} {}", mark, status, todo.title);
+                if let Some(priority) = todo.priority {
+                    content.push_str(&format!(" {}", priority.symbol()));
+                }
+                if let Some(due) = todo.due_date {
+                    content.push_str(&format!(" [due {}]", due));
+                }
+                if let Some(project) = &todo.project {
+                    content.push_str(&format!(" [in {}]", project));
+                }
+                for tag in &todo.tags {
+                    content.push_str(&format!(" #{}", tag));
+                }
+                if let Some(reason) = &todo.status_reason {
+                    content.push_str(&format!(" ({})", reason));
+                }
+                if let Some(deleted) = todo.deleted_at {
+                    content.push_str(&format!(
+                        " [deleted {}]",
+                        deleted.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                    ));
+                }
                 ListItem::new(content).style(style)
             })
             .collect();
 
-        let filter_text = match self.filter {
-            Filter::All => "All",
-            Filter::Completed => "Completed",
-            Filter::Pending => "Pending",
+        let filter_text = match (&self.active_view, &self.query) {
+            (Some(name), _) => format!("@{}", name),
+            (None, Some(query)) if self.filter != Filter::Archived => {
+                format!("{}, where {}", self.filter.label(), query)
+            }
+            _ => self.filter.label().to_string(),
         };
 
-        let todos_list = List::new(todos)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title(format!("Todos ({})", filter_text)),
+        // Only part of a long list is loaded, so show where the cursor is in all of it
+        let position = match self.selected.selected() {
+            Some(i) if self.list_total > self.todos.len() => {
+                format!(" {}/{}", self.list_offset + i + 1, self.list_total)
+            }
+            _ => String::new(),
+        };
+        let list_title = if self.view == View::Trash {
+            format!("Trash ({}) - r=restore, d=delete forever", self.todos.len())
+        } else if self.marked.is_empty() {
+            format!("Todos ({}, by {}){}", filter_text, self.sort, position)
+        } else {
+            format!(
+                "Todos ({}, by {}){} - {} marked",
+                filter_text,
+                self.sort,
+                position,
+                self.marked.len()
             )
-            .highlight_style(Style::default().bg(Color::DarkGray))
-            .highlight_symbol(">> ");
-
-        f.render_stateful_widget(todos_list, chunks[1], &mut self.selected);
-
-        // Status/Input bar
-        let status_text = match self.input_mode {
-            InputMode::Normal => self.status_message.clone(),
-            InputMode::Editing => format!("New todo: {}", self.input),
         };
 
-        let status = Paragraph::new(status_text)
-            .style(match self.input_mode {
-                InputMode::Normal => Style::default(),
-                InputMode::Editing => Style::default().fg(Color::Yellow),
-            })
-            .wrap(Wrap { trim: true })
-            .block(Block::default().borders(Borders::ALL).title("Status"));
+        let todos_list = List::new(todos)
+            .block(Block::default().borders(Borders::ALL).title(list_title))
+            .highlight_style(Style::default().bg(self.theme.highlight()))
+            .highlight_symbol(">> ");
 
-        f.render_widget(status, chunks[2]);
+        self.list_area = area;
+        f.render_stateful_widget(todos_list, area, &mut self.selected);
     }
 }
@@ -0,0 +1,58 @@
+use super::{App, InputMode};
+use crate::database::Query;
+use crate::Result;
+use crossterm::event::KeyCode;
+
+impl App {
+    /// Open the filter bar, starting from the current query
+    pub(super) fn start_query(&mut self) {
+        self.input = self
+            .query
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        self.input_mode = InputMode::Filtering;
+        self.status_message =
+            "Filter, e.g. status:open due<+7d \"text\" (Enter to apply, empty to clear, ESC to cancel)"
+                .to_string();
+    }
+
+    pub(super) async fn handle_query_input(&mut self, key: KeyCode) -> Result<bool> {
+        match key {
+            KeyCode::Enter if self.input.trim().is_empty() => {
+                self.query = None;
+                self.active_view = None;
+                self.input_mode = InputMode::Normal;
+                self.list_offset = 0;
+                self.reload();
+                self.status_message = "Filter cleared".to_string();
+            }
+            KeyCode::Enter => match self.input.parse::<Query>() {
+                Ok(query) => {
+                    self.status_message = format!("Filtering by {}", query);
+                    self.query = Some(query);
+                    self.active_view = None;
+                    self.input.clear();
+                    self.input_mode = InputMode::Normal;
+                    self.list_offset = 0;
+                    self.reload();
+                }
+                // Stay in the filter bar so the query can be corrected
+                Err(err) => self.status_message = format!("Invalid filter: {}", err),
+            },
+            KeyCode::Char(c) => {
+                self.input.push(c);
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Esc => {
+                self.input.clear();
+                self.input_mode = InputMode::Normal;
+                self.status_message = "Cancelled".to_string();
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+}
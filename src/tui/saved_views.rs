@@ -0,0 +1,45 @@
+use super::{App, Filter};
+use crate::database::Query;
+use crate::Result;
+
+impl App {
+    /// Show the todos matching a saved view, replacing the filter and query
+    pub(super) async fn select_view(&mut self, name: &str) -> Result<()> {
+        let Some(view) = self.views.iter().find(|v| v.name == name) else {
+            return Ok(());
+        };
+        match view.query.parse::<Query>() {
+            Ok(query) => {
+                self.status_message = format!("Showing @{}: {}", view.name, view.query);
+                self.active_view = Some(view.name.clone());
+                self.filter = Filter::All;
+                self.query = Some(query);
+                self.list_offset = 0;
+                self.reload();
+            }
+            Err(err) => self.status_message = format!("Invalid view @{}: {}", view.name, err),
+        }
+        Ok(())
+    }
+
+    /// Select the next saved view, or go back to all todos after the last one
+    pub(super) async fn cycle_view(&mut self) -> Result<()> {
+        if self.views.is_empty() {
+            self.status_message =
+                "No saved views; create one with: view save <name> <query>".to_string();
+            return Ok(());
+        }
+        let next = match &self.active_view {
+            Some(active) => self
+                .views
+                .iter()
+                .position(|v| &v.name == active)
+                .map_or(0, |i| i + 1),
+            None => 0,
+        };
+        match self.views.get(next).map(|v| v.name.clone()) {
+            Some(name) => self.select_view(&name).await,
+            None => self.set_filter(Filter::All).await,
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use super::App;
+use crate::database::format_duration;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Sparkline},
+    Frame,
+};
+
+/// Number of days covered by the stats screen
+pub(super) const STATS_DAYS: u32 = 30;
+
+impl App {
+    pub(super) fn render_stats(&self, f: &mut Frame, area: Rect) {
+        let Some(stats) = &self.stats else {
+            f.render_widget(
+                Paragraph::new("Loading...").block(Block::default().borders(Borders::ALL)),
+                area,
+            );
+            return;
+        };
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(Self::STATUS_ROWS),
+                Constraint::Length(6),
+                Constraint::Min(0),
+            ])
+            .split(area);
+        let top = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rows[0]);
+
+        let counts: Vec<Line> = stats
+            .by_status
+            .iter()
+            .map(|(status, count)| {
+                Line::from(format!(
+                    "{} {:<12} {:>5}",
+                    status.symbol(),
+                    status.label(),
+                    count
+                ))
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(counts).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("By status ({} total)", stats.total)),
+            ),
+            top[0],
+        );
+
+        let completed_recently: i64 = stats.per_day.iter().map(|p| p.completed).sum();
+        let created_recently: i64 = stats.per_day.iter().map(|p| p.created).sum();
+        let summary = vec![
+            Line::from(format!(
+                "Completion rate: {:.0}%",
+                stats.completion_rate() * 100.0
+            )),
+            Line::from(format!(
+                "Last {} days: {} created, {} completed ({:.1}/day)",
+                STATS_DAYS,
+                created_recently,
+                completed_recently,
+                completed_recently as f64 / STATS_DAYS as f64
+            )),
+            Line::from(format!(
+                "Average time to complete: {}",
+                stats
+                    .average_completion_time
+                    .map_or("n/a".to_string(), format_duration)
+            )),
+        ];
+        f.render_widget(
+            Paragraph::new(summary).block(Block::default().borders(Borders::ALL).title("Summary")),
+            top[1],
+        );
+
+        let per_day: Vec<u64> = stats.per_day.iter().map(|p| p.completed as u64).collect();
+        f.render_widget(
+            Sparkline::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("Completed per day (last {} days)", STATS_DAYS)),
+                )
+                .data(&per_day)
+                .style(Style::default().fg(Color::Green)),
+            rows[1],
+        );
+
+        let bottom = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rows[2]);
+        let weeks: Vec<Line> = stats
+            .per_week
+            .iter()
+            .rev()
+            .map(|p| {
+                Line::from(format!(
+                    "{}  created {:>3}  completed {:>3}",
+                    p.start, p.created, p.completed
+                ))
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(weeks).block(Block::default().borders(Borders::ALL).title("Per week")),
+            bottom[0],
+        );
+
+        let oldest: Vec<Line> = stats
+            .oldest_open
+            .iter()
+            .map(|todo| {
+                Line::from(format!(
+                    "{} {} ({})",
+                    todo.status.symbol(),
+                    todo.title,
+                    todo.created_at.format("%Y-%m-%d")
+                ))
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(oldest)
+                .block(Block::default().borders(Borders::ALL).title("Oldest open")),
+            bottom[1],
+        );
+    }
+
+    /// Height of the status count block: one row per status plus borders
+    const STATUS_ROWS: u16 = crate::models::Status::ALL.len() as u16 + 2;
+}
@@ -0,0 +1,430 @@
+//! Keeps the in-memory list in step with the database without blocking the UI.
+//!
+//! Common edits (status changes, deletes, adds, renames, moves, undo and redo)
+//! are applied to `App::todos` straight away and their writes queued to a
+//! background worker.
+//! The worker runs jobs one at a time, in order, so a reload queued after a
+//! write always sees it. Once the queue is empty the visible todos are
+//! reloaded in the background to reconcile with the database, e.g. to drop a
+//! completed todo from the Pending filter or to undo a failed write.
+
+use super::{stats, window, App, Filter, Operation, View};
+use crate::database::{Conflict, Query, Sort, Stats, TodoDatabase};
+use crate::models::{Status, Todo};
+use crate::Result;
+use chrono::{Local, NaiveDate};
+use futures::future::BoxFuture;
+use std::collections::HashSet;
+use tokio::sync::mpsc;
+
+/// Database work run by the background worker
+pub(super) type Job = Box<dyn FnOnce(TodoDatabase) -> BoxFuture<'static, Update> + Send>;
+
+/// Result of a background job, sent back to the UI loop
+pub(super) enum Update {
+    /// A queued write to the todos with `ids` finished, with the message to
+    /// show if it failed
+    Saved {
+        ids: Vec<String>,
+        error: Option<String>,
+    },
+    /// A queued update was refused because the todos changed elsewhere
+    Conflict(Box<Conflict>, Vec<Todo>),
+    /// Freshly loaded todos
+    Loaded(Result<Box<Snapshot>>),
+}
+
+/// What to load for the current view
+#[derive(Clone)]
+pub(super) struct LoadRequest {
+    /// Incremented on every local change, so loads started before it are discarded
+    generation: u64,
+    view: View,
+    filter: Filter,
+    query: Option<Query>,
+    sort: Sort,
+    list_offset: usize,
+    calendar_range: (NaiveDate, NaiveDate),
+}
+
+/// Todos loaded for a [`LoadRequest`]
+pub(super) struct Snapshot {
+    generation: u64,
+    todos: Vec<Todo>,
+    list_offset: usize,
+    list_total: usize,
+    stats: Option<Stats>,
+}
+
+impl LoadRequest {
+    pub(super) async fn run(self, db: &TodoDatabase) -> Result<Snapshot> {
+        let mut snapshot = Snapshot {
+            generation: self.generation,
+            todos: Vec::new(),
+            list_offset: 0,
+            list_total: 0,
+            stats: None,
+        };
+        snapshot.todos = match self.view {
+            View::Month | View::Week => {
+                let (start, end) = self.calendar_range;
+                db.get_todos_due_between(start, end).await?
+            }
+            View::List if self.filter == Filter::Archived => db.list_archived(&self.sort).await?,
+            View::List => {
+                let total = db.count_todos(self.query.as_ref()).await? as usize;
+                let offset = if self.list_offset >= total {
+                    total.saturating_sub(window::WINDOW / 2)
+                } else {
+                    self.list_offset
+                };
+                snapshot.list_offset = offset;
+                snapshot.list_total = total;
+                db.list_window(
+                    self.query.as_ref(),
+                    &self.sort,
+                    offset as u64,
+                    window::WINDOW as u32,
+                )
+                .await?
+            }
+            View::Board => match &self.query {
+                Some(query) => db.find_todos(query, &self.sort).await?,
+                None => db.list_todos(&Status::BOARD, &self.sort).await?,
+            },
+            View::Trash => db.list_trash().await?,
+            View::Stats => {
+                let today = Local::now().date_naive();
+                snapshot.stats = Some(db.stats(stats::STATS_DAYS, today).await?);
+                Vec::new()
+            }
+        };
+        // Only the active list is loaded a window at a time
+        if self.view != View::List || self.filter == Filter::Archived {
+            snapshot.list_total = snapshot.todos.len();
+        }
+        Ok(snapshot)
+    }
+}
+
+impl App {
+    pub(super) fn load_request(&self) -> LoadRequest {
+        LoadRequest {
+            generation: self.generation,
+            view: self.view,
+            filter: self.filter,
+            query: self.list_query(self.view_statuses()),
+            sort: self.sort.clone(),
+            list_offset: self.list_offset,
+            calendar_range: self.calendar_range(),
+        }
+    }
+
+    /// Queue a job behind any pending ones, starting the worker on first use
+    fn enqueue(&mut self, job: Job) {
+        let jobs = self.jobs.get_or_insert_with(|| {
+            let (jobs, mut queue) = mpsc::unbounded_channel::<Job>();
+            let updates = self.updates_tx.clone();
+            let db = self.db.clone();
+            tokio::spawn(async move {
+                while let Some(job) = queue.recv().await {
+                    if updates.send(job(db.clone()).await).is_err() {
+                        break;
+                    }
+                }
+            });
+            jobs
+        });
+        // The worker only stops once the app and its sender are gone
+        let _ = jobs.send(job);
+        self.pending += 1;
+    }
+
+    /// Queue a write to the todos with `ids`; the local state should already
+    /// reflect it
+    pub(super) fn save<F>(&mut self, ids: Vec<String>, write: F)
+    where
+        F: FnOnce(TodoDatabase) -> BoxFuture<'static, Result<()>> + Send + 'static,
+    {
+        self.generation += 1;
+        self.enqueue(Box::new(move |db| {
+            Box::pin(async move {
+                let error = write(db)
+                    .await
+                    .err()
+                    .map(|e| format!("Saving failed: {}", e));
+                Update::Saved { ids, error }
+            })
+        }));
+    }
+
+    /// Show updated todos straight away and queue their write, which only
+    /// succeeds if nobody else changed them since they were loaded
+    pub(super) fn save_todos(&mut self, todos: Vec<Todo>) {
+        // The stored versions once the write succeeds
+        let saved: Vec<Todo> = todos
+            .iter()
+            .map(|todo| Todo {
+                version: todo.version + 1,
+                ..todo.clone()
+            })
+            .collect();
+        self.replace_local(&saved);
+        self.generation += 1;
+        self.enqueue(Box::new(move |db| {
+            Box::pin(async move {
+                match db.update_many(&todos).await {
+                    Ok(()) => Update::Saved {
+                        ids: Vec::new(),
+                        error: None,
+                    },
+                    Err(e) => match e.downcast::<Conflict>() {
+                        Ok(conflict) => Update::Conflict(Box::new(conflict), todos),
+                        Err(e) => Update::Saved {
+                            ids: todos.into_iter().map(|t| t.id).collect(),
+                            error: Some(format!("Saving failed: {}", e)),
+                        },
+                    },
+                }
+            })
+        }));
+    }
+
+    /// Show an undo or redo of `op` straight away and queue `write`, the
+    /// operation that makes it, dropping `op` from the history if that fails
+    pub(super) fn save_history(&mut self, op: &Operation, write: Operation, action: &'static str) {
+        self.show_local(&write);
+        self.generation += 1;
+        let ids = op.ids();
+        self.enqueue(Box::new(move |db| {
+            Box::pin(async move {
+                let error =
+                    write
+                        .apply(&db)
+                        .await
+                        .err()
+                        .map(|e| match e.downcast_ref::<Conflict>() {
+                            Some(conflict) => format!(
+                                "Can't {}: '{}' was changed elsewhere",
+                                action, conflict.current.title
+                            ),
+                            None => format!("Can't {}: {}", action, e),
+                        });
+                Update::Saved { ids, error }
+            })
+        }));
+    }
+
+    /// Apply the changes of `op` to the loaded todos that can be shown without
+    /// a reload; added and restored todos appear once the list is reloaded
+    fn show_local(&mut self, op: &Operation) {
+        match op {
+            Operation::Edit { after, .. } | Operation::SetStatus { after, .. } => {
+                self.replace_local(std::slice::from_ref(after))
+            }
+            Operation::Delete(todo) => self.remove_local(std::slice::from_ref(&todo.id)),
+            Operation::Create(_) | Operation::Restore(_) => {}
+            Operation::Bulk(ops) => ops.iter().for_each(|op| self.show_local(op)),
+        }
+    }
+
+    /// Reload the current view in the background, after any pending writes
+    pub(super) fn reload(&mut self) {
+        self.generation += 1;
+        let request = self.load_request();
+        self.enqueue(Box::new(move |db| {
+            Box::pin(async move { Update::Loaded(request.run(&db).await.map(Box::new)) })
+        }));
+    }
+
+    /// Apply a finished background job
+    pub(super) fn apply_update(&mut self, update: Update) {
+        self.pending -= 1;
+        match update {
+            Update::Saved { ids, error } => {
+                if let Some(error) = error {
+                    // Undoing a write that never happened would revert
+                    // whatever is stored instead
+                    self.history.forget(&ids);
+                    // The reload below brings the list back to what is stored
+                    self.status_message = error;
+                }
+                if self.pending == 0 {
+                    self.reload();
+                }
+            }
+            Update::Conflict(conflict, todos) => {
+                let ids: Vec<String> = todos.iter().map(|t| t.id.clone()).collect();
+                self.history.forget(&ids);
+                // Resolved once the user is back in normal mode; the reload
+                // meanwhile shows the other change
+                self.conflicts.push((*conflict, todos));
+                if self.pending == 0 {
+                    self.reload();
+                }
+            }
+            Update::Loaded(Ok(snapshot)) => {
+                if snapshot.generation == self.generation {
+                    self.apply_snapshot(*snapshot);
+                }
+            }
+            // Keep showing what was loaded before
+            Update::Loaded(Err(e)) => self.status_message = format!("Loading failed: {}", e),
+        }
+    }
+
+    /// Wait for all queued jobs, so direct database access sees their writes
+    pub(super) async fn settle(&mut self) {
+        while self.pending > 0 {
+            match self.updates_rx.recv().await {
+                Some(update) => self.apply_update(update),
+                None => break,
+            }
+        }
+    }
+
+    /// Replace the given todos in the loaded list
+    pub(super) fn replace_local(&mut self, todos: &[Todo]) {
+        for todo in todos {
+            if let Some(slot) = self.todos.iter_mut().find(|t| t.id == todo.id) {
+                *slot = todo.clone();
+            }
+        }
+    }
+
+    /// Drop the given todos from the loaded list
+    pub(super) fn remove_local(&mut self, ids: &[String]) {
+        let before = self.todos.len();
+        self.todos.retain(|t| !ids.contains(&t.id));
+        self.list_total = self.list_total.saturating_sub(before - self.todos.len());
+        self.fix_selection();
+    }
+
+    /// Show a new todo at the top of the loaded list until the next reload
+    /// puts it in its sorted place
+    pub(super) fn insert_local(&mut self, todo: Todo) {
+        self.todos.insert(0, todo);
+        self.list_total += 1;
+        self.fix_selection();
+    }
+
+    pub(super) fn apply_snapshot(&mut self, snapshot: Snapshot) {
+        self.todos = snapshot.todos;
+        self.list_offset = snapshot.list_offset;
+        self.list_total = snapshot.list_total;
+        if snapshot.stats.is_some() {
+            self.stats = snapshot.stats;
+        }
+
+        let visible: HashSet<&String> = self.todos.iter().map(|t| &t.id).collect();
+        self.marked.retain(|id| visible.contains(id));
+
+        self.clamp_board_rows();
+        self.scroll_day(0);
+        self.fix_selection();
+    }
+
+    fn fix_selection(&mut self) {
+        if self.todos.is_empty() {
+            self.selected.select(None);
+        } else if let Some(selected) = self.selected.selected() {
+            if selected >= self.todos.len() {
+                self.selected.select(Some(self.todos.len() - 1));
+            }
+        } else {
+            self.selected.select(Some(0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tui::Operation;
+
+    #[tokio::test]
+    async fn test_local_change_then_reconcile() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todo = Todo::new("Test".to_string(), None);
+        db.create_todo(&todo).await.unwrap();
+
+        let mut app = App::new(db.clone()).with_filter(Filter::Pending);
+        app.refresh_todos().await.unwrap();
+        app.set_status(vec![todo.clone()], Status::Done)
+            .await
+            .unwrap();
+
+        // Shown as done straight away, still in the pending list until reloaded
+        assert_eq!(app.todos.len(), 1);
+        assert!(app.todos[0].is_completed());
+        assert_eq!(app.pending, 1);
+
+        app.settle().await;
+        assert!(app.todos.is_empty());
+        let stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+        assert!(stored.is_completed());
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_is_not_undoable() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todo = Todo::new("Test".to_string(), None);
+        db.create_todo(&todo).await.unwrap();
+
+        // Creating a todo with a taken ID fails
+        let mut app = App::new(db.clone());
+        let saved = todo.clone();
+        app.save(vec![todo.id.clone()], move |db| {
+            Box::pin(async move { db.create_todo(&saved).await })
+        });
+        app.history.record(Operation::Create(todo.clone()));
+        app.settle().await;
+
+        assert!(app.status_message.starts_with("Saving failed"));
+        assert!(!app.history.can_undo());
+        assert!(db.get_todo(&todo.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_undo_in_background() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        let todo = Todo::new("Test".to_string(), None);
+        db.create_todo(&todo).await.unwrap();
+
+        let mut app = App::new(db.clone());
+        app.refresh_todos().await.unwrap();
+        app.set_status(vec![todo.clone()], Status::Done)
+            .await
+            .unwrap();
+        app.undo();
+
+        // Shown undone straight away, written once the queue gets to it
+        assert!(!app.todos[0].is_completed());
+        assert_eq!(app.pending, 2);
+        app.redo();
+        app.undo();
+        app.settle().await;
+
+        let stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+        assert!(!stored.is_completed());
+        assert_eq!(stored.version, 5);
+        assert!(app.history.can_redo());
+    }
+
+    #[tokio::test]
+    async fn test_failed_load_keeps_list() {
+        let db = TodoDatabase::new(":memory:").await.unwrap();
+        db.create_todo(&Todo::new("Test".to_string(), None))
+            .await
+            .unwrap();
+
+        let mut app = App::new(db.clone());
+        app.refresh_todos().await.unwrap();
+        db.close().await;
+        app.reload();
+        app.settle().await;
+
+        assert!(app.status_message.starts_with("Loading failed"));
+        assert_eq!(app.todos.len(), 1);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::config::Theme;
+use ratatui::style::Color;
+
+impl Theme {
+    /// Regular todo text
+    pub fn text(self) -> Color {
+        match self {
+            Theme::Dark => Color::White,
+            Theme::Light => Color::Black,
+        }
+    }
+
+    /// Borders of focused panes, the header and the active tab
+    pub fn accent(self) -> Color {
+        match self {
+            Theme::Dark => Color::Cyan,
+            Theme::Light => Color::Blue,
+        }
+    }
+
+    /// Text drawn on top of the accent colour
+    pub fn on_accent(self) -> Color {
+        match self {
+            Theme::Dark => Color::Black,
+            Theme::Light => Color::White,
+        }
+    }
+
+    /// Cancelled todos and days outside the current month
+    pub fn muted(self) -> Color {
+        match self {
+            Theme::Dark => Color::DarkGray,
+            Theme::Light => Color::Gray,
+        }
+    }
+
+    /// Background of the row under the cursor
+    pub fn highlight(self) -> Color {
+        match self {
+            Theme::Dark => Color::DarkGray,
+            Theme::Light => Color::Gray,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use super::{App, InputMode, Operation};
+use crate::models::Todo;
+use crate::Result;
+use crossterm::event::KeyCode;
+
+impl App {
+    /// Handle trash-specific keys, returning whether the key was consumed
+    pub(super) async fn handle_trash_input(&mut self, key: KeyCode) -> Result<bool> {
+        match key {
+            KeyCode::Char('?') => {
+                self.status_message = "Trash: r=restore, d=delete forever, E=empty trash, space=mark, u=undo, T=back to list, q=quit".to_string();
+            }
+            KeyCode::Char('r') => self.restore(self.targets()),
+            KeyCode::Char('d') => self.confirm_purge(self.targets()),
+            KeyCode::Char('E') => self.confirm_purge(self.todos.clone()),
+            // Trashed todos are read-only until restored
+            KeyCode::Char(
+                'e' | 'c' | 'o' | 'i' | 'B' | 'W' | 'X' | '#' | 'M' | 'P' | 'J' | 'K' | 's',
+            ) => {
+                self.status_message = "Restore the todo first (r)".to_string();
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn restore(&mut self, mut todos: Vec<Todo>) {
+        if todos.is_empty() {
+            return;
+        }
+        let ids: Vec<String> = todos.iter().map(|t| t.id.clone()).collect();
+        self.remove_local(&ids);
+        let restored = ids.clone();
+        self.save(ids, move |db| {
+            Box::pin(async move {
+                for id in &restored {
+                    db.restore_todo(id).await?;
+                }
+                Ok(())
+            })
+        });
+        let count = todos.len();
+        let op = match count {
+            1 => Operation::Restore(todos.remove(0)),
+            _ => Operation::Bulk(todos.into_iter().map(Operation::Restore).collect()),
+        };
+        self.history.record(op);
+        self.marked.clear();
+        self.status_message = if count == 1 {
+            "Todo restored".to_string()
+        } else {
+            format!("{} todos restored", count)
+        };
+    }
+
+    /// Ask before permanently deleting, which cannot be undone
+    fn confirm_purge(&mut self, todos: Vec<Todo>) {
+        self.status_message = match todos.as_slice() {
+            [] => return,
+            [todo] => format!(
+                "Delete '{}' forever? This cannot be undone (y/n)",
+                todo.title
+            ),
+            _ => format!(
+                "Delete {} todos forever? This cannot be undone (y/n)",
+                todos.len()
+            ),
+        };
+        self.pending_delete = todos;
+        self.input_mode = InputMode::Confirming;
+    }
+
+    pub(super) fn purge(&mut self, todos: Vec<Todo>) {
+        let ids: Vec<String> = todos.into_iter().map(|t| t.id).collect();
+        self.remove_local(&ids);
+        let count = ids.len();
+        let purged = ids.clone();
+        self.save(ids, move |db| {
+            Box::pin(async move { db.purge_many(&purged).await.map(|_| ()) })
+        });
+        self.marked.clear();
+        self.status_message = format!("{} todos deleted forever", count);
+    }
+}
@@ -0,0 +1,53 @@
+//! The list view keeps only a window of todos around the cursor in memory,
+//! so long lists load quickly; moving past either end of the window fetches
+//! the next one.
+
+use super::App;
+use crate::Result;
+
+/// Number of todos the list view loads at a time
+pub(super) const WINDOW: usize = 500;
+
+impl App {
+    /// Move the cursor by `delta` rows, wrapping around at either end of the list
+    pub(super) async fn step(&mut self, delta: isize) -> Result<()> {
+        if self.list_total == 0 {
+            return Ok(());
+        }
+        let target = match self.selected.selected() {
+            Some(i) => (self.list_offset + i) as isize + delta,
+            None => 0,
+        };
+        self.select_index(target.rem_euclid(self.list_total as isize) as usize)
+            .await
+    }
+
+    /// Move the cursor by `delta` rows without wrapping around
+    pub(super) async fn scroll(&mut self, delta: isize) -> Result<()> {
+        if self.list_total == 0 {
+            return Ok(());
+        }
+        let current = self.list_offset + self.selected.selected().unwrap_or(0);
+        let target = current
+            .saturating_add_signed(delta)
+            .min(self.list_total - 1);
+        self.select_index(target).await
+    }
+
+    /// Select the todo at `index` in the whole list, loading the window
+    /// around it if it is not loaded yet
+    pub(super) async fn select_index(&mut self, index: usize) -> Result<()> {
+        if index < self.list_offset || index >= self.list_offset + self.todos.len() {
+            self.list_offset = index.saturating_sub(WINDOW / 2);
+            // Range marks are relative to the loaded window
+            self.mark_anchor = None;
+            self.refresh_todos().await?;
+        }
+        if let Some(local) = index.checked_sub(self.list_offset) {
+            if local < self.todos.len() {
+                self.selected.select(Some(local));
+            }
+        }
+        Ok(())
+    }
+}
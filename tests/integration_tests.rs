@@ -1,4 +1,8 @@
-use template_rust::models::Todo;
+use template_rust::database::{
+    backup_dir, Conflict, DatabaseOptions, Key, Placement, Query, Sort, TodoDatabase, PAGE_SIZE,
+    SCHEMA_VERSION,
+};
+use template_rust::models::{AuditAction, EventKind, Status, Todo};
 
 #[test]
 fn test_todo_creation() {
@@ -9,7 +13,7 @@ fn test_todo_creation() {
 
     assert_eq!(todo.title, "Test todo");
     assert_eq!(todo.description, Some("Test description".to_string()));
-    assert!(!todo.completed);
+    assert!(!todo.is_completed());
     assert!(!todo.id.is_empty());
 }
 
@@ -23,7 +27,7 @@ fn test_todo_completion() {
 
     todo.complete();
 
-    assert!(todo.completed);
+    assert!(todo.is_completed());
     assert!(todo.updated_at > original_updated_at);
 }
 
@@ -44,3 +48,981 @@ fn test_todo_update() {
     assert_eq!(todo.description, Some("New description".to_string()));
     assert!(todo.updated_at > original_updated_at);
 }
+
+#[tokio::test]
+async fn test_list_todos_sorted() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    for title in ["banana", "Apple", "cherry"] {
+        db.create_todo(&Todo::new(title.to_string(), None))
+            .await
+            .unwrap();
+    }
+
+    let sort: Sort = "title".parse().unwrap();
+    let titles: Vec<String> = db
+        .list_todos(&[], &sort)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.title)
+        .collect();
+    assert_eq!(titles, ["Apple", "banana", "cherry"]);
+
+    let sort: Sort = "title:desc".parse().unwrap();
+    let todos = db.list_todos(&Status::OPEN, &sort).await.unwrap();
+    assert_eq!(todos[0].title, "cherry");
+}
+
+#[tokio::test]
+async fn test_move_todo() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let todos: Vec<Todo> = ["a", "b", "c"]
+        .iter()
+        .map(|t| Todo::new(t.to_string(), None))
+        .collect();
+    for todo in &todos {
+        db.create_todo(todo).await.unwrap();
+    }
+
+    let titles = |todos: Vec<Todo>| todos.into_iter().map(|t| t.title).collect::<Vec<_>>();
+    let manual = Sort::manual();
+    assert_eq!(
+        titles(db.list_todos(&[], &manual).await.unwrap()),
+        ["a", "b", "c"]
+    );
+
+    db.move_todo(&todos[2].id, Placement::Before(&todos[0].id))
+        .await
+        .unwrap();
+    assert_eq!(
+        titles(db.list_todos(&[], &manual).await.unwrap()),
+        ["c", "a", "b"]
+    );
+
+    // Repeatedly splitting the same gap eventually forces a renumber
+    for _ in 0..80 {
+        db.move_todo(&todos[1].id, Placement::After(&todos[2].id))
+            .await
+            .unwrap();
+        db.move_todo(&todos[0].id, Placement::After(&todos[2].id))
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        titles(db.list_todos(&[], &manual).await.unwrap()),
+        ["c", "a", "b"]
+    );
+
+    assert!(db
+        .move_todo(&todos[0].id, Placement::Before(&todos[0].id))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_migrates_legacy_schema() {
+    let path = std::env::temp_dir().join(format!("todo-legacy-{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite://{}?mode=rwc", path.display());
+    {
+        let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+        sqlx::query(
+            "CREATE TABLE todos (id TEXT PRIMARY KEY, title TEXT NOT NULL, description TEXT, \
+             completed BOOLEAN NOT NULL DEFAULT FALSE, created_at TEXT NOT NULL, updated_at TEXT NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO todos VALUES \
+             ('a', 'open', NULL, 0, '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00'), \
+             ('b', 'finished', 'desc', 1, '2024-01-02T00:00:00+00:00', '2024-01-02T00:00:00+00:00')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+    }
+
+    let db = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    let open = db.get_todo("a").await.unwrap().unwrap();
+    let finished = db.get_todo("b").await.unwrap().unwrap();
+    assert_eq!(open.status, Status::Todo);
+    assert_eq!(finished.status, Status::Done);
+    assert_eq!(finished.description.as_deref(), Some("desc"));
+
+    let manual = db.list_todos(&[], &Sort::manual()).await.unwrap();
+    assert_eq!(manual[0].id, "b");
+
+    // The unmigrated file was kept
+    let backups: Vec<_> = std::fs::read_dir(backup_dir(&path))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0].starts_with(&format!(
+        "{}-v0-",
+        path.file_stem().unwrap().to_str().unwrap()
+    )));
+
+    drop(db);
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_dir_all(backup_dir(&path));
+}
+
+#[tokio::test]
+async fn test_todos_due_between() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let date = |d| chrono::NaiveDate::from_ymd_opt(2026, 11, d).unwrap();
+    for (title, due) in [
+        ("late", Some(date(20))),
+        ("soon", Some(date(2))),
+        ("none", None),
+    ] {
+        let mut todo = Todo::new(title.to_string(), None);
+        todo.reschedule(due);
+        db.create_todo(&todo).await.unwrap();
+    }
+
+    let todos = db.get_todos_due_between(date(1), date(7)).await.unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].title, "soon");
+    assert_eq!(todos[0].due_date, Some(date(2)));
+
+    let todos = db.get_todos_due_between(date(1), date(30)).await.unwrap();
+    let titles: Vec<_> = todos.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["soon", "late"]);
+}
+
+#[tokio::test]
+async fn test_stats() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let now = chrono::Utc::now();
+    for (title, status, age_hours) in [
+        ("old", Status::Todo, 72),
+        ("started", Status::InProgress, 10),
+        ("finished", Status::Done, 4),
+        ("dropped", Status::Cancelled, 1),
+    ] {
+        let mut todo = Todo::new(title.to_string(), None);
+        todo.created_at = now - chrono::Duration::hours(age_hours);
+        todo.set_status(status);
+        db.create_todo(&todo).await.unwrap();
+    }
+
+    let today = now.with_timezone(&chrono::Local).date_naive();
+    let stats = db.stats(7, today).await.unwrap();
+    assert_eq!(stats.total, 4);
+    assert_eq!(stats.by_status.len(), Status::ALL.len());
+    assert!(stats.by_status.contains(&(Status::Done, 1)));
+    assert!(stats.by_status.contains(&(Status::Waiting, 0)));
+    assert_eq!(stats.completion_rate(), 0.25);
+
+    assert_eq!(stats.per_day.len(), 7);
+    assert_eq!(stats.per_day.last().unwrap().start, today);
+    assert_eq!(stats.per_day.iter().map(|p| p.created).sum::<i64>(), 4);
+    assert_eq!(stats.per_day.iter().map(|p| p.completed).sum::<i64>(), 1);
+    assert_eq!(stats.per_week.iter().map(|p| p.completed).sum::<i64>(), 1);
+
+    let average = stats.average_completion_time.unwrap();
+    assert!((average - chrono::Duration::hours(4)).num_seconds().abs() < 5);
+
+    let oldest: Vec<_> = stats.oldest_open.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(oldest, ["old", "started"]);
+}
+
+#[tokio::test]
+async fn test_stats_count_local_days() {
+    use chrono::{Local, NaiveTime, TimeZone};
+
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let today = Local::now().date_naive();
+    let yesterday = today.pred_opt().unwrap();
+    let local = |day: chrono::NaiveDate, h, m| {
+        Local
+            .from_local_datetime(&day.and_time(NaiveTime::from_hms_opt(h, m, 0).unwrap()))
+            .earliest()
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    };
+    // Either side of local midnight, whatever day that is in UTC
+    for (title, created_at) in [
+        ("late", local(yesterday, 23, 30)),
+        ("early", local(today, 0, 30)),
+    ] {
+        let mut todo = Todo::new(title.to_string(), None);
+        todo.created_at = created_at;
+        db.create_todo(&todo).await.unwrap();
+    }
+
+    let stats = db.stats(2, today).await.unwrap();
+    let created: Vec<_> = stats.per_day.iter().map(|p| (p.start, p.created)).collect();
+    assert_eq!(created, [(yesterday, 1), (today, 1)]);
+}
+
+#[tokio::test]
+async fn test_todo_events() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let mut todo = Todo::new("Write report".to_string(), None);
+    db.create_todo(&todo).await.unwrap();
+
+    todo.set_status(Status::Blocked);
+    todo.status_reason = Some("waiting on data".to_string());
+    db.update_todo(&todo).await.unwrap();
+    todo.version += 1;
+    todo.complete();
+    todo.update(Some("Write final report".to_string()), None);
+    db.update_many(std::slice::from_ref(&todo)).await.unwrap();
+    db.delete_todo(&todo.id).await.unwrap();
+
+    let events = db.todo_events(&todo.id).await.unwrap();
+    let kinds: Vec<_> = events.iter().map(|e| e.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            EventKind::Created,
+            EventKind::Status,
+            EventKind::Status,
+            EventKind::Renamed,
+            EventKind::Deleted
+        ]
+    );
+    assert_eq!(events[1].new_value.as_deref(), Some("blocked"));
+    assert_eq!(events[1].note.as_deref(), Some("waiting on data"));
+    assert_eq!(events[2].old_value.as_deref(), Some("blocked"));
+    assert_eq!(events[2].new_value.as_deref(), Some("done"));
+    assert_eq!(
+        events[3].to_string(),
+        "renamed: Write report -> Write final report"
+    );
+}
+
+#[tokio::test]
+async fn test_trash() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let keep = Todo::new("Keep".to_string(), None);
+    let oops = Todo::new("Oops".to_string(), None);
+    let gone = Todo::new("Gone".to_string(), None);
+    for todo in [&keep, &oops, &gone] {
+        db.create_todo(todo).await.unwrap();
+    }
+
+    db.delete_many(&[oops.id.clone(), gone.id.clone()])
+        .await
+        .unwrap();
+    assert!(db.get_todo(&oops.id).await.unwrap().is_none());
+    let titles: Vec<_> = db
+        .get_all_todos()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.title)
+        .collect();
+    assert_eq!(titles, ["Keep"]);
+    assert_eq!(
+        db.stats(7, chrono::Utc::now().date_naive())
+            .await
+            .unwrap()
+            .total,
+        1
+    );
+
+    let trash = db.list_trash().await.unwrap();
+    assert_eq!(trash.len(), 2);
+    assert!(trash.iter().all(|t| t.deleted_at.is_some()));
+
+    assert!(db.restore_todo(&oops.id).await.unwrap());
+    assert!(!db.restore_todo(&oops.id).await.unwrap());
+    assert!(db
+        .get_todo(&oops.id)
+        .await
+        .unwrap()
+        .unwrap()
+        .deleted_at
+        .is_none());
+
+    let old = chrono::Utc::now() - chrono::Duration::days(30);
+    assert_eq!(db.empty_trash(Some(old)).await.unwrap(), 0);
+    assert_eq!(db.empty_trash(None).await.unwrap(), 1);
+    assert!(db.list_trash().await.unwrap().is_empty());
+
+    let kinds: Vec<_> = db
+        .todo_events(&gone.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [EventKind::Created, EventKind::Deleted, EventKind::Purged]
+    );
+}
+
+#[tokio::test]
+async fn test_archive_completed() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let now = chrono::Utc::now();
+    let mut old = Todo::new("Old".to_string(), None);
+    old.complete();
+    old.completed_at = Some(now - chrono::Duration::days(40));
+    let mut recent = Todo::new("Recent".to_string(), None);
+    recent.complete();
+    let open = Todo::new("Open".to_string(), None);
+    for todo in [&old, &recent, &open] {
+        db.create_todo(todo).await.unwrap();
+    }
+
+    let cutoff = (now - chrono::Duration::days(30)).date_naive();
+    assert_eq!(db.archive_completed(Some(cutoff)).await.unwrap(), 1);
+    let archived = db.list_archived(&Sort::default()).await.unwrap();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].title, "Old");
+    assert!(archived[0].archived_at.is_some());
+    assert_eq!(db.get_all_todos().await.unwrap().len(), 2);
+    assert!(db.get_todo(&old.id).await.unwrap().is_some());
+
+    assert!(!db.archive_todo(&open.id).await.unwrap());
+    assert!(db.archive_todo(&recent.id).await.unwrap());
+    assert_eq!(db.archive_completed(None).await.unwrap(), 0);
+    assert_eq!(db.get_all_todos().await.unwrap().len(), 1);
+    let sort: Sort = "title".parse().unwrap();
+    let page = db.list_archived_window(&sort, 1, 5).await.unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].title, "Recent");
+
+    assert!(db.unarchive_todo(&old.id).await.unwrap());
+    assert!(!db.unarchive_todo(&old.id).await.unwrap());
+    assert_eq!(db.list_archived(&Sort::default()).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_reopen_archived() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let mut todo = Todo::new("Finished too soon".to_string(), None);
+    todo.complete();
+    db.create_todo(&todo).await.unwrap();
+    assert!(db.archive_todo(&todo.id).await.unwrap());
+
+    // As `todo start` does
+    let mut stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+    stored.set_status(Status::InProgress);
+    db.update_todo(&stored).await.unwrap();
+
+    let open = db
+        .list_todos(&Status::OPEN, &Sort::default())
+        .await
+        .unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!(open[0].status, Status::InProgress);
+    assert!(open[0].archived_at.is_none());
+    assert!(db.list_archived(&Sort::default()).await.unwrap().is_empty());
+    let kinds: Vec<_> = db
+        .todo_events(&todo.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.kind)
+        .collect();
+    assert_eq!(kinds.last(), Some(&EventKind::Unarchived));
+
+    // Closed todos stay archived whatever else changes
+    let mut stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+    stored.set_status(Status::Cancelled);
+    db.update_todo(&stored).await.unwrap();
+    assert!(db.archive_todo(&todo.id).await.unwrap());
+    let mut stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+    stored.update(Some("Dropped".to_string()), None);
+    db.update_todo(&stored).await.unwrap();
+    assert_eq!(db.list_archived(&Sort::default()).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_audit_log() {
+    let db = TodoDatabase::new(":memory:")
+        .await
+        .unwrap()
+        .with_actor("alice");
+    let mut todo = Todo::new("Audit me".to_string(), None);
+    db.create_todo(&todo).await.unwrap();
+    todo.complete();
+    db.update_todo(&todo).await.unwrap();
+
+    let bob = db.clone().with_actor("bob");
+    bob.delete_todo(&todo.id).await.unwrap();
+
+    let entries = db.audit_log(None, None).await.unwrap();
+    let actions: Vec<_> = entries
+        .iter()
+        .map(|e| (e.actor.as_str(), e.action))
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            ("alice", AuditAction::Create),
+            ("alice", AuditAction::Update),
+            ("bob", AuditAction::Delete)
+        ]
+    );
+
+    let update: serde_json::Value = serde_json::from_str(&entries[1].diff).unwrap();
+    assert_eq!(update["before"]["status"], "todo");
+    assert_eq!(update["after"]["status"], "done");
+    assert!(update["after"].get("title").is_none());
+    let created: serde_json::Value = serde_json::from_str(&entries[0].diff).unwrap();
+    assert!(created["before"].is_null());
+    assert_eq!(created["after"]["title"], "Audit me");
+
+    let by_bob = db.audit_log(None, Some("bob")).await.unwrap();
+    assert_eq!(by_bob.len(), 1);
+    let future = chrono::Utc::now() + chrono::Duration::hours(1);
+    assert!(db.audit_log(Some(future), None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_find_todos() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let today = chrono::NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+    let mut report = Todo::new("Write report".to_string(), Some("100% done".to_string()));
+    report.due_date = Some(today);
+    let mut milk = Todo::new("Buy milk".to_string(), None);
+    milk.complete();
+    let call = Todo::new("Call Bob".to_string(), None);
+    for todo in [&report, &milk, &call] {
+        db.create_todo(todo).await.unwrap();
+    }
+
+    let titles = |query: &str| {
+        let query = Query::parse(query, today).unwrap();
+        let db = db.clone();
+        async move {
+            let todos = db.find_todos(&query, &Sort::manual()).await.unwrap();
+            todos.into_iter().map(|t| t.title).collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(
+        titles("status:open and (due<=today or title:bob)").await,
+        vec!["Write report", "Call Bob"]
+    );
+    assert_eq!(titles("\"100%\"").await, vec!["Write report"]);
+    assert_eq!(
+        titles("\"0%\" or b").await,
+        vec!["Write report", "Buy milk", "Call Bob"]
+    );
+    assert_eq!(
+        titles("not due:any and -status:closed").await,
+        vec!["Call Bob"]
+    );
+    assert_eq!(titles("completed<2000-01-01").await, Vec::<String>::new());
+
+    db.archive_todo(&milk.id).await.unwrap();
+    assert_eq!(titles("milk").await, Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn test_find_todos_by_local_day() {
+    use chrono::{Local, NaiveTime, TimeZone};
+
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let today = Local::now().date_naive();
+    let yesterday = today.pred_opt().unwrap();
+    let local = |day: chrono::NaiveDate, h, m| {
+        Local
+            .from_local_datetime(&day.and_time(NaiveTime::from_hms_opt(h, m, 0).unwrap()))
+            .earliest()
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    };
+    for (title, completed_at) in [
+        ("late", local(yesterday, 23, 30)),
+        ("early", local(today, 0, 30)),
+    ] {
+        let mut todo = Todo::new(title.to_string(), None);
+        todo.complete();
+        todo.completed_at = Some(completed_at);
+        db.create_todo(&todo).await.unwrap();
+    }
+
+    for (query, expected) in [("completed:today", "early"), ("completed<today", "late")] {
+        let query = Query::parse(query, today).unwrap();
+        let todos = db.find_todos(&query, &Sort::manual()).await.unwrap();
+        let titles: Vec<_> = todos.into_iter().map(|t| t.title).collect();
+        assert_eq!(titles, [expected], "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn test_saved_views() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    assert!(db.list_views().await.unwrap().is_empty());
+
+    let view = db
+        .save_view("urgent", "status:open and due<+7d")
+        .await
+        .unwrap();
+    assert_eq!(view.query, "status:open and due<+7d");
+    db.save_view("milk", "title:milk").await.unwrap();
+    assert!(db.save_view("bad name", "milk").await.is_err());
+    assert!(db.save_view("tags", "tag:work").await.is_err());
+
+    let updated = db.save_view("urgent", "due<today").await.unwrap();
+    assert_eq!(updated.created_at, view.created_at);
+    let names: Vec<String> = db
+        .list_views()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|v| v.name)
+        .collect();
+    assert_eq!(names, vec!["milk", "urgent"]);
+    assert_eq!(
+        db.get_view("urgent").await.unwrap().unwrap().query,
+        "due<today"
+    );
+
+    assert!(db.delete_view("milk").await.unwrap());
+    assert!(!db.delete_view("milk").await.unwrap());
+    assert!(db.get_view("milk").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_pagination() {
+    use futures::TryStreamExt;
+
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let base = chrono::NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+    for i in 0..PAGE_SIZE as usize + 3 {
+        // Repeated and differently cased titles and missing due dates make ties
+        let title = if i % 2 == 0 { "task" } else { "Task" };
+        let mut todo = Todo::new(format!("{} {}", title, i % 7), None);
+        if i % 3 != 0 {
+            todo.due_date = Some(base + chrono::Duration::days((i % 5) as i64));
+        }
+        if i % 4 == 0 {
+            todo.complete();
+        }
+        db.create_todo(&todo).await.unwrap();
+    }
+    let total = db.count_todos(None).await.unwrap() as usize;
+    assert_eq!(total, PAGE_SIZE as usize + 3);
+
+    for sort in [
+        "title,due:desc",
+        "due",
+        "completion,status:desc,title",
+        "manual",
+    ] {
+        let sort: Sort = sort.parse().unwrap();
+        let all: Vec<String> = db
+            .list_todos(&[], &sort)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+
+        let mut paged = Vec::new();
+        let mut after: Option<String> = None;
+        loop {
+            let page = db
+                .list_after(None, &sort, after.as_deref(), 97)
+                .await
+                .unwrap();
+            let Some(last) = page.last() else { break };
+            after = Some(last.id.clone());
+            paged.extend(page.into_iter().map(|t| t.id));
+        }
+        assert_eq!(paged, all, "keyset pages for {}", sort);
+
+        let window = db.list_window(None, &sort, 10, 5).await.unwrap();
+        let window: Vec<String> = window.into_iter().map(|t| t.id).collect();
+        assert_eq!(window, all[10..15]);
+
+        let streamed: Vec<String> = db
+            .stream_todos(None, sort.clone())
+            .map_ok(|t| t.id)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(streamed, all);
+    }
+
+    let open = Query::status(&Status::OPEN);
+    let open_count = db.count_todos(Some(&open)).await.unwrap() as usize;
+    assert_eq!(open_count, total - total.div_ceil(4));
+    let first = db
+        .list_after(Some(&open), &Sort::default(), None, 3)
+        .await
+        .unwrap();
+    assert!(first.iter().all(|t| !t.status.is_closed()));
+}
+
+#[tokio::test]
+async fn test_watch_changes() {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    let path = std::env::temp_dir().join(format!("todo-watch-{}.db", uuid::Uuid::new_v4()));
+    let watcher = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    let other = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+
+    let mut changes = Box::pin(watcher.changes(Duration::from_millis(10)).await.unwrap());
+    let quiet = tokio::time::timeout(Duration::from_millis(100), changes.next()).await;
+    assert!(quiet.is_err());
+
+    other
+        .create_todo(&Todo::new("From elsewhere".to_string(), None))
+        .await
+        .unwrap();
+    let change = tokio::time::timeout(Duration::from_secs(5), changes.next()).await;
+    assert!(matches!(change, Ok(Some(Ok(())))));
+
+    drop(changes);
+    drop(watcher);
+    drop(other);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_batches_and_transactions() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let todos: Vec<Todo> = (0..3)
+        .map(|i| Todo::new(format!("Todo {}", i), None))
+        .collect();
+    db.create_many(&todos).await.unwrap();
+    assert_eq!(db.get_all_todos().await.unwrap().len(), 3);
+    let manual = db.list_todos(&[], &Sort::manual()).await.unwrap();
+    assert_eq!(manual[2].id, todos[2].id);
+
+    // A failing transaction leaves nothing behind
+    let extra = Todo::new("Extra".to_string(), None);
+    let result = db
+        .transaction(|tx| {
+            Box::pin(async {
+                tx.create_todo(&extra).await?;
+                tx.delete_todo(&todos[0].id).await?;
+                assert!(tx.get_todo(&todos[0].id).await?.is_none());
+                tx.create_todo(&extra).await
+            })
+        })
+        .await;
+    assert!(result.is_err());
+    assert!(db.get_todo(&extra.id).await.unwrap().is_none());
+    assert!(db.get_todo(&todos[0].id).await.unwrap().is_some());
+    assert!(db.todo_events(&extra.id).await.unwrap().is_empty());
+
+    let count = db
+        .transaction(|tx| {
+            Box::pin(async {
+                tx.create_todo(&extra).await?;
+                let mut renamed = todos[1].clone();
+                renamed.update(Some("Renamed".to_string()), None);
+                tx.update_todo(&renamed).await?;
+                Ok(2)
+            })
+        })
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+    assert!(db.get_todo(&extra.id).await.unwrap().is_some());
+    let renamed = db.get_todo(&todos[1].id).await.unwrap().unwrap();
+    assert_eq!(renamed.title, "Renamed");
+}
+
+#[tokio::test]
+async fn test_update_conflict() {
+    let db = TodoDatabase::new(":memory:").await.unwrap();
+    let todo = Todo::new("Shared".to_string(), None);
+    db.create_todo(&todo).await.unwrap();
+
+    let mut ours = db.get_todo(&todo.id).await.unwrap().unwrap();
+    let mut theirs = ours.clone();
+    assert_eq!(ours.version, 1);
+
+    theirs.update(Some("Theirs".to_string()), None);
+    db.update_todo(&theirs).await.unwrap();
+
+    ours.update(Some("Ours".to_string()), None);
+    let err = db.update_todo(&ours).await.unwrap_err();
+    let conflict = err.downcast_ref::<Conflict>().unwrap();
+    assert_eq!(conflict.expected, 1);
+    assert_eq!(conflict.current.version, 2);
+    assert_eq!(conflict.current.title, "Theirs");
+
+    // A batch with one outdated todo changes nothing
+    let other = Todo::new("Other".to_string(), None);
+    db.create_todo(&other).await.unwrap();
+    let mut renamed = other.clone();
+    renamed.update(Some("Renamed".to_string()), None);
+    assert!(db.update_many(&[renamed, ours.clone()]).await.is_err());
+    assert_eq!(
+        db.get_todo(&other.id).await.unwrap().unwrap().title,
+        "Other"
+    );
+
+    db.overwrite_todo(&ours).await.unwrap();
+    let stored = db.get_todo(&todo.id).await.unwrap().unwrap();
+    assert_eq!(stored.title, "Ours");
+    assert_eq!(stored.version, 3);
+
+    // Trashed and missing todos are not found rather than silently skipped
+    db.delete_todo(&todo.id).await.unwrap();
+    let err = db.overwrite_todo(&stored).await.unwrap_err();
+    assert!(err.to_string().contains("not found"), "{}", err);
+    let missing = Todo::new("Missing".to_string(), None);
+    assert!(db.update_todo(&missing).await.is_err());
+}
+
+/// Several handles on one file, as separate processes would have, writing at once
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_writers() {
+    let path = std::env::temp_dir().join(format!("todo-stress-{}.db", uuid::Uuid::new_v4()));
+    let path = path.to_str().unwrap().to_string();
+    let options = DatabaseOptions {
+        busy_timeout: std::time::Duration::from_millis(50),
+        busy_retries: 20,
+        ..DatabaseOptions::default()
+    };
+    TodoDatabase::open(&path, options.clone()).await.unwrap();
+
+    const WRITERS: usize = 6;
+    const TODOS: usize = 20;
+    let mut tasks = Vec::new();
+    for writer in 0..WRITERS {
+        let path = path.clone();
+        let options = options.clone();
+        tasks.push(tokio::spawn(async move {
+            let db = TodoDatabase::open(&path, options).await?;
+            for i in 0..TODOS {
+                let todo = Todo::new(format!("Writer {} todo {}", writer, i), None);
+                db.create_todo(&todo).await?;
+                // Reads then writes in one transaction, the case the busy
+                // timeout alone can't handle
+                let mut stored = db.get_todo(&todo.id).await?.unwrap();
+                stored.complete();
+                db.update_todo(&stored).await?;
+                db.list_todos(&[], &Sort::default()).await?;
+            }
+            template_rust::Result::Ok(())
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    let db = TodoDatabase::open(&path, options).await.unwrap();
+    let todos = db.get_all_todos().await.unwrap();
+    assert_eq!(todos.len(), WRITERS * TODOS);
+    assert!(todos.iter().all(|todo| todo.is_completed()));
+
+    drop(db);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+    }
+}
+
+#[tokio::test]
+async fn test_backup_restore_check() {
+    let dir = std::env::temp_dir().join(format!("todo-backup-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("todo.db");
+    let backup = dir.join("copy.db");
+    let db = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    let kept = Todo::new("Kept".to_string(), None);
+    db.create_todo(&kept).await.unwrap();
+
+    db.backup(&backup).await.unwrap();
+    assert!(db.backup(&backup).await.is_err());
+    assert!(db.check().await.unwrap().is_empty());
+
+    // Damage the live database after the backup
+    db.delete_todo(&kept.id).await.unwrap();
+    let url = format!("sqlite://{}", path.display());
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    sqlx::query("UPDATE todos SET created_at = 'yesterday', status = 'someday'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let problems = db.check().await.unwrap();
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    assert!(problems[1].contains("invalid created_at"));
+    pool.close().await;
+    db.close().await;
+
+    let version = TodoDatabase::restore(&path, &backup, None).await.unwrap();
+    assert_eq!(version, SCHEMA_VERSION);
+    let db = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    assert!(db.get_todo(&kept.id).await.unwrap().is_some());
+    assert!(db.check().await.unwrap().is_empty());
+    db.close().await;
+
+    // Backups from a newer app version are refused
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", backup.display()))
+        .await
+        .unwrap();
+    sqlx::query(&format!("PRAGMA user_version = {}", SCHEMA_VERSION + 1))
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+    let err = TodoDatabase::restore(&path, &backup, None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("newer"));
+    assert!(TodoDatabase::restore(&path, &dir.join("missing.db"), None)
+        .await
+        .is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_restore_baseline_backup() {
+    let dir = std::env::temp_dir().join(format!("todo-baseline-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("todo.db");
+    let backup = dir.join("baseline.db");
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}?mode=rwc", backup.display()))
+        .await
+        .unwrap();
+    sqlx::query(
+        "CREATE TABLE todos (id TEXT PRIMARY KEY, title TEXT NOT NULL, description TEXT, \
+         completed BOOLEAN NOT NULL DEFAULT FALSE, created_at TEXT NOT NULL, updated_at TEXT NOT NULL)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO todos VALUES \
+         ('a', 'open', NULL, 0, '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00'), \
+         ('b', 'finished', NULL, 1, '2024-01-02T00:00:00+00:00', '2024-01-02T00:00:00+00:00')",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    pool.close().await;
+
+    let db = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    db.create_todo(&Todo::new("Replaced".to_string(), None))
+        .await
+        .unwrap();
+    db.close().await;
+
+    let version = TodoDatabase::restore(&path, &backup, None).await.unwrap();
+    assert_eq!(version, 0);
+    // Migrated when opened
+    let db = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    let todos = db.list_todos(&[], &Sort::manual()).await.unwrap();
+    assert_eq!(todos.len(), 2);
+    assert_eq!(
+        db.get_todo("b").await.unwrap().unwrap().status,
+        Status::Done
+    );
+    db.close().await;
+
+    // Any other SQLite file is still refused
+    let other = dir.join("other.db");
+    let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}?mode=rwc", other.display()))
+        .await
+        .unwrap();
+    sqlx::query("CREATE TABLE notes (body TEXT)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    pool.close().await;
+    let err = TodoDatabase::restore(&path, &other, None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not a todo database"), "{}", err);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_restore_refuses_open_database() {
+    let dir = std::env::temp_dir().join(format!("todo-restore-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("todo.db");
+    let backup = dir.join("copy.db");
+    let db = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    db.backup(&backup).await.unwrap();
+    let todo = Todo::new("Only in the log".to_string(), None);
+    db.create_todo(&todo).await.unwrap();
+
+    let err = TodoDatabase::restore(&path, &backup, None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("in use"), "{}", err);
+    assert!(db.get_todo(&todo.id).await.unwrap().is_some());
+    db.close().await;
+
+    // Nothing written before the refusal was lost with the log
+    let db = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    assert!(db.get_todo(&todo.id).await.unwrap().is_some());
+    db.close().await;
+
+    TodoDatabase::restore(&path, &backup, None).await.unwrap();
+    let db = TodoDatabase::new(path.to_str().unwrap()).await.unwrap();
+    assert!(db.get_todo(&todo.id).await.unwrap().is_none());
+    db.close().await;
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_encryption() {
+    let dir = std::env::temp_dir().join(format!("todo-encrypted-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("todo.db");
+    let url = path.to_str().unwrap();
+    let db = TodoDatabase::new(url).await.unwrap();
+    let todo = Todo::new("Customer incident 4711".to_string(), None);
+    db.create_todo(&todo).await.unwrap();
+    db.close().await;
+
+    let keyed = |key: &Key| DatabaseOptions {
+        key: Some(key.clone()),
+        ..DatabaseOptions::default()
+    };
+    let passphrase = Key::Passphrase("correct horse's battery".to_string());
+    TodoDatabase::encrypt(&path, &passphrase).await.unwrap();
+    let contents = std::fs::read(&path).unwrap();
+    assert!(!contents.windows(8).any(|window| window == b"Customer"));
+    let err = TodoDatabase::new(url).await.unwrap_err();
+    assert!(err.to_string().contains("encrypted"), "{}", err);
+
+    let db = TodoDatabase::open(url, keyed(&passphrase)).await.unwrap();
+    assert!(db.get_todo(&todo.id).await.unwrap().is_some());
+    assert!(db.check().await.unwrap().is_empty());
+    let backup = dir.join("copy.db");
+    db.backup(&backup).await.unwrap();
+    db.close().await;
+
+    // Backups stay encrypted and restore with the same key
+    assert!(TodoDatabase::restore(&path, &backup, None).await.is_err());
+    TodoDatabase::restore(&path, &backup, Some(&passphrase))
+        .await
+        .unwrap();
+    assert!(TodoDatabase::new(url).await.is_err());
+
+    let key_file = dir.join("todo.key");
+    std::fs::write(&key_file, [0u8, 159, 146, 150, 255]).unwrap();
+    let key_file = Key::File(key_file);
+    TodoDatabase::rekey(&path, &passphrase, &key_file)
+        .await
+        .unwrap();
+    let err = TodoDatabase::open(url, keyed(&passphrase))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("wrong key"), "{}", err);
+    let db = TodoDatabase::open(url, keyed(&key_file)).await.unwrap();
+    assert!(db.get_todo(&todo.id).await.unwrap().is_some());
+    db.close().await;
+
+    TodoDatabase::decrypt(&path, &key_file).await.unwrap();
+    let db = TodoDatabase::new(url).await.unwrap();
+    assert_eq!(
+        db.get_todo(&todo.id).await.unwrap().unwrap().title,
+        todo.title
+    );
+    assert!(db.check().await.unwrap().is_empty());
+    db.close().await;
+
+    let _ = std::fs::remove_dir_all(&dir);
+}